@@ -0,0 +1,156 @@
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::Path;
+
+use flate2::read::GzDecoder;
+
+use crate::utils::is_text_extension;
+
+/// Text read from a single archive member is capped at this many bytes.
+const MAX_MEMBER_TEXT_BYTES: u64 = 64 * 1024;
+
+/// Compression of a tar archive.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TarKind {
+    Plain,
+    Gzip,
+}
+
+impl TarKind {
+    /// Recognizes `.tar`, `.tar.gz` and `.tgz` file names.
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let name = path.file_name()?.to_string_lossy().to_lowercase();
+        if name.ends_with(".tar.gz") || name.ends_with(".tgz") {
+            Some(TarKind::Gzip)
+        } else if name.ends_with(".tar") {
+            Some(TarKind::Plain)
+        } else {
+            None
+        }
+    }
+}
+
+/// Files and text found in a tar archive.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TarContents {
+    /// Regular files in the archive.
+    pub file_count: usize,
+    /// Uncompressed size of those files, in bytes.
+    pub total_size: u64,
+    /// Text of the members with a text extension, separated by blank lines.
+    pub text: String,
+}
+
+/// Reads a tar stream, compressed as `kind` says.
+///
+/// Every regular file is counted. Members with a text extension also
+/// contribute their first 64 KiB of text.
+pub fn read_tar<R: Read>(reader: R, kind: TarKind) -> io::Result<TarContents> {
+    match kind {
+        TarKind::Plain => read_entries(tar::Archive::new(reader)),
+        TarKind::Gzip => read_entries(tar::Archive::new(GzDecoder::new(reader))),
+    }
+}
+
+/// Reads the tar archive at `path`, guessing its compression from the name.
+pub fn read_tar_file(path: &Path) -> io::Result<TarContents> {
+    let kind = TarKind::from_path(path).ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("not a tar archive: {}", path.display()),
+        )
+    })?;
+    read_tar(File::open(path)?, kind)
+}
+
+fn read_entries<R: Read>(mut archive: tar::Archive<R>) -> io::Result<TarContents> {
+    let mut contents = TarContents::default();
+    for entry in archive.entries()? {
+        let entry = entry?;
+        if !entry.header().entry_type().is_file() {
+            continue;
+        }
+        contents.file_count += 1;
+        contents.total_size += entry.size();
+
+        let is_text = entry
+            .path()?
+            .extension()
+            .is_some_and(|e| is_text_extension(&e.to_string_lossy()));
+        if !is_text {
+            continue;
+        }
+        let mut buf = Vec::new();
+        entry.take(MAX_MEMBER_TEXT_BYTES).read_to_end(&mut buf)?;
+        let text = String::from_utf8_lossy(&buf);
+        let text = text.trim();
+        if !text.is_empty() {
+            if !contents.text.is_empty() {
+                contents.text.push_str("\n\n");
+            }
+            contents.text.push_str(text);
+        }
+    }
+    Ok(contents)
+}
+
+#[cfg(test)]
+fn tar_fixture(files: &[(&str, &[u8])]) -> Vec<u8> {
+    let mut builder = tar::Builder::new(Vec::new());
+    for (name, data) in files {
+        let mut header = tar::Header::new_gnu();
+        header.set_size(data.len() as u64);
+        header.set_mode(0o644);
+        header.set_cksum();
+        builder.append_data(&mut header, name, *data).unwrap();
+    }
+    builder.into_inner().unwrap()
+}
+
+#[test]
+fn test_tar_kind_from_path() {
+    assert_eq!(
+        TarKind::from_path(Path::new("logs.tar")),
+        Some(TarKind::Plain)
+    );
+    assert_eq!(
+        TarKind::from_path(Path::new("src.TAR.GZ")),
+        Some(TarKind::Gzip)
+    );
+    assert_eq!(
+        TarKind::from_path(Path::new("backup.tgz")),
+        Some(TarKind::Gzip)
+    );
+    assert_eq!(TarKind::from_path(Path::new("photos.zip")), None);
+    assert_eq!(TarKind::from_path(Path::new("notes.gz")), None);
+}
+
+#[test]
+fn test_read_tar_gz_counts_files_and_extracts_text() {
+    use flate2::write::GzEncoder;
+    use std::io::Write;
+
+    let tar = tar_fixture(&[
+        ("project/README.md", b"# Release notes\n"),
+        ("project/logo.png", &[0x89, b'P', b'N', b'G', 0, 0, 0, 0]),
+        ("project/build.log", b"build ok\n"),
+    ]);
+    let mut encoder = GzEncoder::new(Vec::new(), flate2::Compression::default());
+    encoder.write_all(&tar).unwrap();
+    let gz = encoder.finish().unwrap();
+
+    let contents = read_tar(gz.as_slice(), TarKind::Gzip).unwrap();
+    assert_eq!(contents.file_count, 3);
+    assert_eq!(contents.total_size, 16 + 8 + 9);
+    assert_eq!(contents.text, "# Release notes\n\nbuild ok");
+}
+
+#[test]
+fn test_read_tar_plain_and_invalid() {
+    let tar = tar_fixture(&[("data.bin", &[0, 1, 2])]);
+    let contents = read_tar(tar.as_slice(), TarKind::Plain).unwrap();
+    assert_eq!(contents.file_count, 1);
+    assert!(contents.text.is_empty());
+
+    assert!(read_tar(&b"not a gzip stream"[..], TarKind::Gzip).is_err());
+}
@@ -0,0 +1,153 @@
+use std::collections::BTreeSet;
+use std::io;
+
+use serde::{Deserialize, Serialize};
+
+use crate::utils::StatePaths;
+
+/// Name of the checkpoint file inside the state directory.
+pub const CHECKPOINT_FILE_NAME: &str = "index-checkpoint.json";
+
+/// Checkpoint settings, loaded from the `[checkpoint]` section of the config.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CheckpointConfig {
+    /// Files indexed between two checkpoint writes; 0 disables checkpoints.
+    pub every: usize,
+}
+
+impl Default for CheckpointConfig {
+    fn default() -> Self {
+        Self { every: 100 }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct CheckpointFile {
+    completed: BTreeSet<String>,
+}
+
+/// Hashes of the files an indexing run has already completed.
+///
+/// A run records each indexed file and the set is written to disk every
+/// `every` files, so an interrupted run can resume where it stopped. A
+/// clean run deletes the file with [`IndexCheckpoint::finish`].
+#[derive(Debug)]
+pub struct IndexCheckpoint {
+    state: StatePaths,
+    every: usize,
+    completed: BTreeSet<String>,
+    unsaved: usize,
+}
+
+impl IndexCheckpoint {
+    /// Opens the checkpoint at [`StatePaths::index_checkpoint`], resuming
+    /// from it unless `force` is set or checkpoints are disabled.
+    pub fn open(state: &StatePaths, config: &CheckpointConfig, force: bool) -> io::Result<Self> {
+        let completed = if force || config.every == 0 {
+            BTreeSet::new()
+        } else {
+            match std::fs::read(state.index_checkpoint()) {
+                Ok(bytes) => {
+                    serde_json::from_slice::<CheckpointFile>(&bytes)
+                        .map_err(io::Error::from)?
+                        .completed
+                }
+                Err(e) if e.kind() == io::ErrorKind::NotFound => BTreeSet::new(),
+                Err(e) => return Err(e),
+            }
+        };
+        Ok(Self {
+            state: state.clone(),
+            every: config.every,
+            completed,
+            unsaved: 0,
+        })
+    }
+
+    /// Whether the file with this content hash was already indexed.
+    pub fn is_done(&self, hash: &str) -> bool {
+        self.completed.contains(hash)
+    }
+
+    /// Records an indexed file, writing the checkpoint every `every` files.
+    pub fn record(&mut self, hash: &str) -> io::Result<()> {
+        if self.every == 0 || !self.completed.insert(hash.to_string()) {
+            return Ok(());
+        }
+        self.unsaved += 1;
+        if self.unsaved >= self.every {
+            self.save()?;
+        }
+        Ok(())
+    }
+
+    /// Writes the checkpoint now, replacing the previous one atomically.
+    pub fn save(&mut self) -> io::Result<()> {
+        if self.every == 0 {
+            return Ok(());
+        }
+        let path = self
+            .state
+            .ensure_file_parent(self.state.index_checkpoint())?;
+        let file = CheckpointFile {
+            completed: self.completed.clone(),
+        };
+        let tmp = path.with_extension("json.tmp");
+        std::fs::write(&tmp, serde_json::to_vec(&file).map_err(io::Error::from)?)?;
+        std::fs::rename(&tmp, &path)?;
+        self.unsaved = 0;
+        Ok(())
+    }
+
+    /// Deletes the checkpoint once a run completed cleanly.
+    pub fn finish(self) -> io::Result<()> {
+        match std::fs::remove_file(self.state.index_checkpoint()) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e),
+            _ => Ok(()),
+        }
+    }
+}
+
+#[test]
+fn test_checkpoint_second_run_skips_completed_files() {
+    let base = std::env::temp_dir().join(format!("cognify-checkpoint-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&base);
+    let state = StatePaths::new(&base, None);
+    let path = state.index_checkpoint();
+    let config = CheckpointConfig { every: 2 };
+
+    // First run is interrupted after three files; only two were written.
+    let mut first = IndexCheckpoint::open(&state, &config, false).unwrap();
+    for hash in ["aaa", "bbb", "ccc"] {
+        first.record(hash).unwrap();
+    }
+    drop(first);
+
+    let second = IndexCheckpoint::open(&state, &config, false).unwrap();
+    assert!(second.is_done("aaa"));
+    assert!(second.is_done("bbb"));
+    assert!(!second.is_done("ccc"));
+
+    let forced = IndexCheckpoint::open(&state, &config, true).unwrap();
+    assert!(!forced.is_done("aaa"));
+
+    second.finish().unwrap();
+    assert!(!path.exists());
+    std::fs::remove_dir_all(&base).unwrap();
+}
+
+#[test]
+fn test_checkpoint_disabled_never_writes() {
+    let base = std::env::temp_dir().join(format!("cognify-nocheckpoint-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&base);
+    let state = StatePaths::new(&base, None);
+    let path = state.index_checkpoint();
+
+    let mut checkpoint =
+        IndexCheckpoint::open(&state, &CheckpointConfig { every: 0 }, false).unwrap();
+    checkpoint.record("aaa").unwrap();
+    checkpoint.save().unwrap();
+    assert!(!path.exists());
+    assert!(!checkpoint.is_done("aaa"));
+}
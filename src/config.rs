@@ -0,0 +1,561 @@
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use serde::Deserialize;
+
+use crate::checkpoint::CheckpointConfig;
+use crate::embeddings::EmbeddingConfig;
+use crate::organizer::OrganizerConfig;
+use crate::tagging::TagOptions;
+use crate::utils::{ExtractionConfig, StatePaths, WalkOptions};
+
+/// Settings loaded from a `cognifs.toml` file.
+///
+/// Every section and key is optional; missing ones keep their defaults.
+/// Named presets under `[profiles.<name>]` hold the same sections and are
+/// overlaid onto the base settings when selected.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// Where checkpoints, undo logs, caches and plans are kept; see
+    /// [`StatePaths::new`].
+    pub state_dir: Option<PathBuf>,
+    pub tagging: TagOptions,
+    pub embedding: EmbeddingConfig,
+    pub checkpoint: CheckpointConfig,
+    pub walk: WalkOptions,
+    pub extraction: ExtractionConfig,
+    pub organizer: OrganizerConfig,
+}
+
+/// Failure to read or parse a config file.
+#[derive(Debug)]
+pub enum ConfigError {
+    Io(std::io::Error),
+    Parse(toml::de::Error),
+    UnknownProfile {
+        name: String,
+        available: Vec<String>,
+    },
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::Io(e) => write!(f, "cannot read config: {}", e),
+            ConfigError::Parse(e) => write!(f, "invalid config: {}", e),
+            ConfigError::UnknownProfile { name, available } if available.is_empty() => {
+                write!(f, "unknown profile `{}`: no profiles are defined", name)
+            }
+            ConfigError::UnknownProfile { name, available } => write!(
+                f,
+                "unknown profile `{}`, available profiles: {}",
+                name,
+                available.join(", ")
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ConfigError::Io(e) => Some(e),
+            ConfigError::Parse(e) => Some(e),
+            ConfigError::UnknownProfile { .. } => None,
+        }
+    }
+}
+
+impl Config {
+    pub fn from_toml_str(content: &str) -> Result<Self, ConfigError> {
+        Self::from_toml_str_with_profile(content, None)
+    }
+
+    /// Parses `content`, overlaying the `[profiles.<profile>]` section onto
+    /// the base settings when a profile is given.
+    ///
+    /// Tables merge key by key and any other profile value replaces the
+    /// base one, so a profile only lists what it changes.
+    pub fn from_toml_str_with_profile(
+        content: &str,
+        profile: Option<&str>,
+    ) -> Result<Self, ConfigError> {
+        let mut table: toml::Table = content.parse().map_err(ConfigError::Parse)?;
+        let profiles = match table.remove("profiles") {
+            None => toml::Table::new(),
+            Some(toml::Value::Table(profiles)) => profiles,
+            Some(_) => {
+                return Err(ConfigError::Parse(serde::de::Error::custom(
+                    "`profiles` must be a table of named profiles",
+                )))
+            }
+        };
+
+        if let Some(name) = profile {
+            match profiles.get(name) {
+                Some(toml::Value::Table(overlay)) => merge_tables(&mut table, overlay),
+                Some(_) => {
+                    return Err(ConfigError::Parse(serde::de::Error::custom(format!(
+                        "profile `{}` must be a table",
+                        name
+                    ))))
+                }
+                None => {
+                    return Err(ConfigError::UnknownProfile {
+                        name: name.to_string(),
+                        available: profiles.keys().cloned().collect(),
+                    })
+                }
+            }
+        }
+        let mut config: Self = toml::Value::Table(table)
+            .try_into()
+            .map_err(ConfigError::Parse)?;
+        config.walk.state_dir = config.state_dir.clone();
+        Ok(config)
+    }
+
+    /// Locations of the persistent artifacts for the tree rooted at `base`.
+    pub fn state_paths(&self, base: &Path) -> StatePaths {
+        StatePaths::new(base, self.state_dir.as_deref())
+    }
+
+    pub fn load(path: &Path) -> Result<Self, ConfigError> {
+        Self::load_with_profile(path, None)
+    }
+
+    pub fn load_with_profile(path: &Path, profile: Option<&str>) -> Result<Self, ConfigError> {
+        let content = std::fs::read_to_string(path).map_err(ConfigError::Io)?;
+        Self::from_toml_str_with_profile(&content, profile)
+    }
+}
+
+fn merge_tables(base: &mut toml::Table, overlay: &toml::Table) {
+    for (key, value) in overlay {
+        match (base.get_mut(key), value) {
+            (Some(toml::Value::Table(base)), toml::Value::Table(overlay)) => {
+                merge_tables(base, overlay)
+            }
+            _ => {
+                base.insert(key.clone(), value.clone());
+            }
+        }
+    }
+}
+
+#[test]
+fn test_config_empty_uses_defaults() {
+    let config = Config::from_toml_str("").unwrap();
+    assert!(config.tagging.blocklist.is_empty());
+    assert!(!config.embedding.strict_dims);
+    assert!(config.embedding.reject_zero_vectors);
+    assert!(!config.embedding.include_path_context);
+}
+
+#[test]
+fn test_config_tagging_section() {
+    let config = Config::from_toml_str(
+        r#"
+        [tagging]
+        blocklist = ["misc", "temp"]
+        "#,
+    )
+    .unwrap();
+    assert_eq!(config.tagging.blocklist, vec!["misc", "temp"]);
+
+    let tags = crate::tagging::finalize_tags(["Misc", "invoice"], &config.tagging);
+    assert_eq!(tags, vec!["invoice"]);
+}
+
+#[test]
+fn test_config_rejects_unknown_keys() {
+    assert!(matches!(
+        Config::from_toml_str("[tagging]\nblock_list = []"),
+        Err(ConfigError::Parse(_))
+    ));
+}
+
+#[test]
+fn test_config_embedding_strict_dims() {
+    let config = Config::from_toml_str("[embedding]\nstrict_dims = true").unwrap();
+    assert!(config.embedding.strict_dims);
+}
+
+#[test]
+fn test_config_embedding_skip_extensions() {
+    let config =
+        Config::from_toml_str("[embedding]\nskip_extensions = [\"zip\", \"iso\"]").unwrap();
+    assert!(!config.embedding.should_embed(Path::new("backup.zip")));
+    assert!(config.embedding.should_embed(Path::new("notes.md")));
+}
+
+#[test]
+fn test_config_checkpoint_section() {
+    assert_eq!(Config::default().checkpoint.every, 100);
+    let config = Config::from_toml_str("[checkpoint]\nevery = 0").unwrap();
+    assert_eq!(config.checkpoint.every, 0);
+}
+
+#[test]
+fn test_config_embedding_model_by_category() {
+    let config = Config::from_toml_str(
+        r#"
+        [embedding]
+        model = "bge-m3"
+
+        [embedding.model_by_category]
+        code = "nomic-embed-code"
+        "#,
+    )
+    .unwrap();
+    assert_eq!(
+        config.embedding.model_for(&["code".to_string()]),
+        "nomic-embed-code"
+    );
+    assert_eq!(config.embedding.model_for(&["photo".to_string()]), "bge-m3");
+}
+
+#[test]
+fn test_config_walk_extension_filter() {
+    let config = Config::from_toml_str(
+        r#"
+        [walk]
+        include_ext = ["PDF", ".md"]
+        exclude_ext = ["md"]
+        "#,
+    )
+    .unwrap();
+    let filter = config.walk.extension_filter();
+    assert!(filter.matches(Path::new("report.pdf")));
+    assert!(filter.matches(Path::new("notes.md")));
+    assert!(!filter.matches(Path::new("photo.jpg")));
+}
+
+#[test]
+fn test_config_walk_exclude_dirs() {
+    let config =
+        Config::from_toml_str("[walk]\nexclude_dirs = [\"**/cache\", \"Downloads/tmp\"]").unwrap();
+    assert_eq!(
+        config.walk.exclude_dirs.patterns(),
+        ["**/cache", "Downloads/tmp"]
+    );
+    assert!(config.walk.exclude_dirs.is_excluded(Path::new("a/cache")));
+
+    let err = Config::from_toml_str("[walk]\nexclude_dirs = [\"a/[b\"]").unwrap_err();
+    assert!(matches!(err, ConfigError::Parse(_)));
+}
+
+#[test]
+fn test_config_extraction_section() {
+    let config = Config::from_toml_str(
+        r#"
+        [extraction]
+        log_sample_bytes = 4096
+        sample_threshold_bytes = 1048576
+        "#,
+    )
+    .unwrap();
+    assert_eq!(config.extraction.log_sample_bytes(), 4096);
+    assert_eq!(config.extraction.sample_threshold_bytes(), 1024 * 1024);
+
+    assert!(matches!(
+        Config::from_toml_str("[extraction]\nlog_sample_bytes = 0"),
+        Err(ConfigError::Parse(_))
+    ));
+}
+
+#[test]
+fn test_config_embedding_chunking() {
+    use crate::embeddings::chunk::LongTextStrategy;
+
+    let config = Config::from_toml_str(
+        r#"
+        [embedding.chunking]
+        chunk_size = 2000
+        chunk_overlap = 100
+        long_text_strategy = "head"
+        "#,
+    )
+    .unwrap();
+    assert_eq!(config.embedding.chunking.chunk_size(), 2000);
+    assert_eq!(config.embedding.chunking.chunk_overlap(), 100);
+    assert_eq!(
+        config.embedding.chunking.long_text_strategy(),
+        LongTextStrategy::Head
+    );
+
+    for invalid in [
+        "[embedding.chunking]\nchunk_size = 0",
+        "[embedding.chunking]\nchunk_size = 100\nchunk_overlap = 100",
+        "[embedding.chunking]\nlong_text_strategy = \"tail\"",
+    ] {
+        assert!(matches!(
+            Config::from_toml_str(invalid),
+            Err(ConfigError::Parse(_))
+        ));
+    }
+}
+
+#[test]
+fn test_config_profile_overlays_base() {
+    let content = r#"
+        [tagging]
+        blocklist = ["misc"]
+
+        [embedding]
+        strict_dims = true
+        skip_extensions = ["zip"]
+
+        [profiles.photos.walk]
+        include_ext = ["jpg", "png"]
+
+        [profiles.docs.embedding]
+        skip_extensions = ["iso"]
+        "#;
+
+    let base = Config::from_toml_str(content).unwrap();
+    assert!(base.walk.include_ext.is_empty());
+    assert_eq!(base.embedding.skip_extensions, vec!["zip"]);
+
+    let docs = Config::from_toml_str_with_profile(content, Some("docs")).unwrap();
+    assert_eq!(docs.embedding.skip_extensions, vec!["iso"]);
+    assert!(docs.embedding.strict_dims);
+    assert_eq!(docs.tagging.blocklist, vec!["misc"]);
+
+    let photos = Config::from_toml_str_with_profile(content, Some("photos")).unwrap();
+    assert_eq!(photos.walk.include_ext, vec!["jpg", "png"]);
+    assert_eq!(photos.embedding.skip_extensions, vec!["zip"]);
+}
+
+#[test]
+fn test_config_unknown_profile_lists_available() {
+    let content = "[profiles.photos]\n[profiles.docs]\n";
+    let err = Config::from_toml_str_with_profile(content, Some("music")).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "unknown profile `music`, available profiles: docs, photos"
+    );
+
+    let err = Config::from_toml_str_with_profile("", Some("music")).unwrap_err();
+    assert!(matches!(err, ConfigError::UnknownProfile { .. }));
+
+    assert!(matches!(
+        Config::from_toml_str_with_profile(
+            "[profiles.docs.tagging]\nblock_list = []",
+            Some("docs")
+        ),
+        Err(ConfigError::Parse(_))
+    ));
+}
+
+#[test]
+fn test_config_embedding_source() {
+    use crate::embeddings::EmbeddingSource;
+
+    let config = Config::from_toml_str("[embedding]\nsource = \"text+tags\"").unwrap();
+    assert_eq!(config.embedding.source, EmbeddingSource::TextAndTags);
+    assert_eq!(
+        Config::from_toml_str("").unwrap().embedding.source,
+        EmbeddingSource::Text
+    );
+    assert!(Config::from_toml_str("[embedding]\nsource = \"title\"").is_err());
+}
+
+#[test]
+fn test_config_embedding_dimension() {
+    let config =
+        Config::from_toml_str("[embedding]\ndimension = 768\ndimension_strict = true").unwrap();
+    assert_eq!(config.embedding.dimension, Some(768));
+    assert!(config.embedding.dimension_strict);
+    assert_eq!(Config::from_toml_str("").unwrap().embedding.dimension, None);
+}
+
+#[test]
+fn test_config_tagging_unknown_tag() {
+    let config = Config::from_toml_str(
+        "[tagging]\nunknown_tag = \"unsorted\"\ndrop_unknown_when_tagged = false",
+    )
+    .unwrap();
+    assert_eq!(config.tagging.unknown_tag, "unsorted");
+    assert!(!config.tagging.drop_unknown_when_tagged);
+}
+
+#[test]
+fn test_config_tagging_dictionary() {
+    use crate::tagging::DictionaryMode;
+
+    let config = Config::from_toml_str(
+        "[tagging]\ndictionary_file = \"medical.toml\"\ndictionary_mode = \"replace\"",
+    )
+    .unwrap();
+    assert_eq!(
+        config.tagging.dictionary_file.as_deref(),
+        Some(Path::new("medical.toml"))
+    );
+    assert_eq!(config.tagging.dictionary_mode, DictionaryMode::Replace);
+}
+
+#[test]
+fn test_config_organizer_untagged_strategy() {
+    use crate::organizer::UntaggedStrategy;
+
+    let config =
+        Config::from_toml_str("[organizer]\nuntagged_strategy = \"by-extension\"").unwrap();
+    assert_eq!(
+        config.organizer.untagged_strategy,
+        UntaggedStrategy::ByExtension
+    );
+    assert!(Config::from_toml_str("[organizer]\nuntagged_strategy = \"by-size\"").is_err());
+}
+
+#[test]
+fn test_config_tagging_scan() {
+    let config =
+        Config::from_toml_str("[tagging.scan]\nwindow_bytes = 4096\nmin_occurrences = 3").unwrap();
+    assert_eq!(config.tagging.scan.window_bytes, 4096);
+    assert_eq!(config.tagging.scan.min_occurrences, 3);
+}
+
+#[test]
+fn test_config_tagging_categories() {
+    let config = Config::from_toml_str(
+        r#"
+        [tagging.categories]
+        top_level = ["medical"]
+        replace = true
+        "#,
+    )
+    .unwrap();
+    let categories = &config.tagging.categories;
+    assert_eq!(
+        categories.categorize(&["medical", "invoice"]),
+        vec!["medical"]
+    );
+}
+
+#[test]
+fn test_config_walk_only_under() {
+    let config = Config::from_toml_str("[walk]\nonly_under = [\"uncategorized\"]").unwrap();
+    assert!(config
+        .walk
+        .is_only_under(Path::new("uncategorized/scan.pdf")));
+    assert!(!config.walk.is_only_under(Path::new("financial/a.pdf")));
+}
+
+#[test]
+fn test_config_embedding_rate_limit() {
+    let config = Config::from_toml_str("[embedding]\nmax_rps = 5.0\nburst = 2").unwrap();
+    assert_eq!(config.embedding.max_rps.get(), 5.0);
+    assert_eq!(config.embedding.burst.get(), 2);
+    assert!(config.embedding.rate_limiter().is_some());
+    assert!(Config::from_toml_str("")
+        .unwrap()
+        .embedding
+        .rate_limiter()
+        .is_none());
+
+    for invalid in [
+        "max_rps = -1.0",
+        "max_rps = 1e300",
+        "max_rps = inf",
+        "max_rps = nan",
+        "burst = 0",
+    ] {
+        assert!(matches!(
+            Config::from_toml_str(&format!("[embedding]\n{}", invalid)),
+            Err(ConfigError::Parse(_))
+        ));
+    }
+}
+
+#[test]
+fn test_config_walk_traversal_options() {
+    let config = Config::from_toml_str(
+        r#"
+        [walk]
+        include_hidden = true
+        follow_symlinks = true
+        max_depth = 3
+        hash = false
+        ignore = ["*.tmp"]
+        skip_protected = true
+        "#,
+    )
+    .unwrap();
+    assert!(config.walk.include_hidden);
+    assert!(config.walk.follow_symlinks);
+    assert_eq!(config.walk.max_depth, Some(3));
+    assert!(!config.walk.hash);
+    assert!(config.walk.ignore.is_excluded(Path::new("a/b.tmp")));
+    assert!(config.walk.skip_protected);
+
+    let defaults = Config::from_toml_str("").unwrap();
+    assert!(defaults.walk.hash);
+    assert!(!defaults.walk.include_hidden);
+}
+
+#[test]
+fn test_config_embedding_quantize() {
+    assert!(!Config::from_toml_str("").unwrap().embedding.quantize);
+    let config = Config::from_toml_str("[embedding]\nquantize = true").unwrap();
+    assert!(config.embedding.quantize);
+}
+
+#[test]
+fn test_config_state_dir() {
+    let base = Path::new("/data/docs");
+    let config = Config::from_toml_str("").unwrap();
+    assert_eq!(
+        config.state_paths(base).root(),
+        Path::new("/data/docs/.cognifs")
+    );
+
+    let config = Config::from_toml_str("state_dir = \"/var/lib/cognifs\"").unwrap();
+    assert_eq!(
+        config.state_paths(base).undo_log(),
+        Path::new("/var/lib/cognifs/undo.log")
+    );
+    assert_eq!(
+        config.walk.state_dir.as_deref(),
+        Some(Path::new("/var/lib/cognifs"))
+    );
+}
+
+#[test]
+fn test_config_embedding_cache_dir() {
+    let config = Config::from_toml_str("[embedding]\ncache_dir = \"cache\"").unwrap();
+    let base = Path::new("/data/docs");
+    assert_eq!(
+        config
+            .embedding
+            .cache(base, &config.state_paths(base))
+            .dir(),
+        Path::new("/data/docs/cache")
+    );
+}
+
+#[test]
+fn test_config_embedding_retry() {
+    let config = Config::from_toml_str(
+        r#"
+        [embedding.retry]
+        max_retries = 5
+        initial_backoff_ms = 100
+        "#,
+    )
+    .unwrap();
+    assert_eq!(config.embedding.retry.max_retries, 5);
+    assert_eq!(config.embedding.retry.initial_backoff_ms, 100);
+    assert_eq!(Config::default().embedding.retry.max_retries, 3);
+}
+
+#[test]
+fn test_config_embedding_circuit_breaker() {
+    let config = Config::from_toml_str(
+        "[embedding.circuit_breaker]\nfailure_threshold = 5\ncooldown_secs = 60",
+    )
+    .unwrap();
+    assert_eq!(config.embedding.circuit_breaker.failure_threshold, 5);
+    assert_eq!(config.embedding.circuit_breaker.cooldown_secs, 60);
+}
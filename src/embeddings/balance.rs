@@ -0,0 +1,201 @@
+use std::num::NonZeroU32;
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// Weight of the newest sample in each server's latency average.
+pub const LATENCY_EMA_ALPHA: f64 = 0.2;
+
+/// Scale of the weights derived from latency, so that integer rounding
+/// keeps their ratios close.
+const LATENCY_WEIGHT_SCALE: f64 = 1_000_000.0;
+
+/// Picks which of several embedding servers gets the next request.
+///
+/// Selection is smooth weighted round-robin: over any run of calls each
+/// server is picked in proportion to its weight, and picks of the same
+/// server are spread out rather than bunched. Without weights every server
+/// has weight 1, which is plain round-robin in URL order. With
+/// `prefer_fast` set and no weights, servers are weighted by the inverse of
+/// their latency average once every server has reported one.
+#[derive(Debug)]
+pub struct ServerPool {
+    urls: Vec<String>,
+    weights: Option<Vec<NonZeroU32>>,
+    prefer_fast: bool,
+    state: Mutex<PoolState>,
+}
+
+#[derive(Debug)]
+struct PoolState {
+    current: Vec<i64>,
+    latency_ema: Vec<Option<f64>>,
+}
+
+impl ServerPool {
+    /// Creates a pool over `servers`, URLs with an optional weight. A
+    /// weight on any server switches the pool to weighted selection, with
+    /// unweighted servers counting as 1.
+    pub fn new<S: Into<String>>(servers: Vec<(S, Option<NonZeroU32>)>) -> Self {
+        let weighted = servers.iter().any(|(_, weight)| weight.is_some());
+        let (urls, weights): (Vec<String>, Vec<NonZeroU32>) = servers
+            .into_iter()
+            .map(|(url, weight)| (url.into(), weight.unwrap_or(NonZeroU32::MIN)))
+            .unzip();
+        let count = urls.len();
+        Self {
+            urls,
+            weights: weighted.then_some(weights),
+            prefer_fast: false,
+            state: Mutex::new(PoolState {
+                current: vec![0; count],
+                latency_ema: vec![None; count],
+            }),
+        }
+    }
+
+    /// Biases selection toward servers with a lower latency average when
+    /// no weights were given.
+    pub fn with_prefer_fast(mut self, prefer_fast: bool) -> Self {
+        self.prefer_fast = prefer_fast;
+        self
+    }
+
+    pub fn urls(&self) -> &[String] {
+        &self.urls
+    }
+
+    /// Index into [`Self::urls`] of the server for the next request, or
+    /// `None` for an empty pool.
+    pub fn next_index(&self) -> Option<usize> {
+        let mut state = self.lock();
+        let weights = self.effective_weights(&state);
+        let total: i64 = weights.iter().sum();
+        for (current, weight) in state.current.iter_mut().zip(&weights) {
+            *current += weight;
+        }
+        // Ties go to the earliest server, which keeps equal weights in URL
+        // order.
+        let chosen = (0..state.current.len()).reduce(|best, i| {
+            if state.current[i] > state.current[best] {
+                i
+            } else {
+                best
+            }
+        })?;
+        state.current[chosen] -= total;
+        Some(chosen)
+    }
+
+    /// URL of the server for the next request.
+    pub fn next_url(&self) -> Option<&str> {
+        self.next_index().map(|i| self.urls[i].as_str())
+    }
+
+    /// Folds the latency of a completed request into the server's
+    /// exponential moving average.
+    pub fn record_latency(&self, index: usize, latency: Duration) {
+        let mut state = self.lock();
+        let Some(ema) = state.latency_ema.get_mut(index) else {
+            return;
+        };
+        let sample = latency.as_secs_f64();
+        *ema = Some(match *ema {
+            Some(average) => average + LATENCY_EMA_ALPHA * (sample - average),
+            None => sample,
+        });
+    }
+
+    /// Latency average of the server at `index`, once it reported one.
+    pub fn latency(&self, index: usize) -> Option<Duration> {
+        self.lock()
+            .latency_ema
+            .get(index)
+            .copied()
+            .flatten()
+            .map(Duration::from_secs_f64)
+    }
+
+    fn effective_weights(&self, state: &PoolState) -> Vec<i64> {
+        if let Some(weights) = &self.weights {
+            return weights.iter().map(|w| i64::from(w.get())).collect();
+        }
+        let latencies: Option<Vec<f64>> = state.latency_ema.iter().copied().collect();
+        match latencies {
+            Some(latencies) if self.prefer_fast => latencies
+                .iter()
+                .map(|secs| (LATENCY_WEIGHT_SCALE / secs.max(1e-6)).round().max(1.0) as i64)
+                .collect(),
+            _ => vec![1; self.urls.len()],
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, PoolState> {
+        self.state
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+#[cfg(test)]
+fn pick_counts(pool: &ServerPool, calls: usize) -> Vec<usize> {
+    let mut counts = vec![0; pool.urls().len()];
+    for _ in 0..calls {
+        counts[pool.next_index().unwrap()] += 1;
+    }
+    counts
+}
+
+#[test]
+fn test_server_pool_without_weights_is_round_robin() {
+    let pool = ServerPool::new(vec![("a", None), ("b", None), ("c", None)]);
+    let picks: Vec<&str> = (0..6).map(|_| pool.next_url().unwrap()).collect();
+    assert_eq!(picks, vec!["a", "b", "c", "a", "b", "c"]);
+
+    let empty = ServerPool::new(Vec::<(String, _)>::new());
+    assert_eq!(empty.next_index(), None);
+}
+
+#[test]
+fn test_server_pool_distribution_matches_weights() {
+    let weight = |w| NonZeroU32::new(w);
+    let pool = ServerPool::new(vec![
+        ("fast", weight(5)),
+        ("medium", weight(2)),
+        ("slow", None),
+    ]);
+    assert_eq!(pick_counts(&pool, 8_000), vec![5_000, 2_000, 1_000]);
+
+    // Picks of the heavy server are interleaved, not bunched.
+    let pool = ServerPool::new(vec![("a", weight(2)), ("b", weight(1))]);
+    let picks: Vec<usize> = (0..6).map(|_| pool.next_index().unwrap()).collect();
+    assert_eq!(picks, vec![0, 1, 0, 0, 1, 0]);
+}
+
+#[test]
+fn test_server_pool_latency_ema() {
+    let pool = ServerPool::new(vec![("a", None), ("b", None)]);
+    assert_eq!(pool.latency(0), None);
+    pool.record_latency(0, Duration::from_millis(100));
+    pool.record_latency(0, Duration::from_millis(200));
+    let ema = pool.latency(0).unwrap().as_secs_f64();
+    assert!((ema - 0.12).abs() < 1e-9, "{}", ema);
+    pool.record_latency(5, Duration::from_millis(1));
+}
+
+#[test]
+fn test_server_pool_prefers_fast_servers() {
+    let pool = ServerPool::new(vec![("a", None), ("b", None)]).with_prefer_fast(true);
+    pool.record_latency(0, Duration::from_millis(10));
+    // Until every server has a latency, selection stays round-robin.
+    assert_eq!(pick_counts(&pool, 100), vec![50, 50]);
+
+    pool.record_latency(1, Duration::from_millis(30));
+    let counts = pick_counts(&pool, 4_000);
+    assert!((2_990..=3_010).contains(&counts[0]), "{:?}", counts);
+
+    // Without the bias, latencies do not change the plain rotation.
+    let plain = ServerPool::new(vec![("a", None), ("b", None)]);
+    plain.record_latency(0, Duration::from_millis(10));
+    plain.record_latency(1, Duration::from_millis(30));
+    assert_eq!(pick_counts(&plain, 100), vec![50, 50]);
+}
@@ -0,0 +1,144 @@
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::utils::sanitize_component;
+
+/// On-disk cache of embeddings keyed by file content hash, model and
+/// dimension. Hashes are those of [`crate::utils::compute_file_hash`].
+///
+/// Entries live at `<dir>/<model>/<dimension>/<hash>.json`, so switching
+/// models or dimensions never returns a stale vector. A missing,
+/// unreadable or malformed entry is a miss and is overwritten on the next
+/// [`Self::put`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EmbeddingCache {
+    dir: PathBuf,
+}
+
+impl EmbeddingCache {
+    pub fn new(dir: PathBuf) -> Self {
+        Self { dir }
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    fn entry_path(&self, hash: &str, model: &str, dimension: usize) -> PathBuf {
+        self.dir
+            .join(sanitize_component(model))
+            .join(dimension.to_string())
+            .join(format!("{}.json", sanitize_component(hash)))
+    }
+
+    /// Returns the cached embedding of the file with content `hash`.
+    pub fn get(&self, hash: &str, model: &str, dimension: usize) -> Option<Vec<f32>> {
+        let bytes = std::fs::read(self.entry_path(hash, model, dimension)).ok()?;
+        serde_json::from_slice::<Vec<f32>>(&bytes)
+            .ok()
+            .filter(|embedding| embedding.len() == dimension)
+    }
+
+    /// Stores `embedding` for the file with content `hash`, replacing any
+    /// previous entry atomically.
+    pub fn put(&self, hash: &str, model: &str, embedding: &[f32]) -> io::Result<()> {
+        let path = self.entry_path(hash, model, embedding.len());
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let tmp = path.with_extension("json.tmp");
+        std::fs::write(
+            &tmp,
+            serde_json::to_vec(embedding).map_err(io::Error::from)?,
+        )?;
+        std::fs::rename(&tmp, &path)
+    }
+
+    /// Returns the cached embedding, or calls `compute` on a miss and
+    /// caches what it returns.
+    ///
+    /// Without a known `dimension` every lookup misses, but the computed
+    /// embedding is still stored under its own length.
+    pub fn get_or_compute<F>(
+        &self,
+        hash: &str,
+        model: &str,
+        dimension: Option<usize>,
+        compute: F,
+    ) -> io::Result<Option<Vec<f32>>>
+    where
+        F: FnOnce() -> Option<Vec<f32>>,
+    {
+        if let Some(cached) = dimension.and_then(|dims| self.get(hash, model, dims)) {
+            return Ok(Some(cached));
+        }
+        let Some(embedding) = compute() else {
+            return Ok(None);
+        };
+        self.put(hash, model, &embedding)?;
+        Ok(Some(embedding))
+    }
+}
+
+#[cfg(test)]
+fn temp_cache(name: &str) -> EmbeddingCache {
+    let dir = std::env::temp_dir().join(format!("cognify-{}-{}", name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    EmbeddingCache::new(dir)
+}
+
+#[test]
+fn test_embedding_cache_hit_skips_compute() {
+    let cache = temp_cache("embed-cache");
+    let first = cache
+        .get_or_compute("abc123", "nomic-embed-text", Some(3), || {
+            Some(vec![0.1, 0.2, 0.3])
+        })
+        .unwrap();
+    assert_eq!(first, Some(vec![0.1, 0.2, 0.3]));
+
+    let second = cache
+        .get_or_compute("abc123", "nomic-embed-text", Some(3), || {
+            panic!("cached embedding was recomputed")
+        })
+        .unwrap();
+    assert_eq!(second, first);
+    std::fs::remove_dir_all(cache.dir()).unwrap();
+}
+
+#[test]
+fn test_embedding_cache_keyed_by_model_and_dimension() {
+    let cache = temp_cache("embed-cache-key");
+    cache
+        .put("abc123", "nomic-embed-text", &[1.0, 0.0])
+        .unwrap();
+
+    assert_eq!(
+        cache.get("abc123", "nomic-embed-text", 2),
+        Some(vec![1.0, 0.0])
+    );
+    assert_eq!(cache.get("abc123", "qwen3-embedding", 2), None);
+    assert_eq!(cache.get("abc123", "nomic-embed-text", 768), None);
+    assert_eq!(cache.get("def456", "nomic-embed-text", 2), None);
+    std::fs::remove_dir_all(cache.dir()).unwrap();
+}
+
+#[test]
+fn test_embedding_cache_malformed_entry_is_a_miss() {
+    let cache = temp_cache("embed-cache-bad");
+    cache.put("abc123", "m", &[1.0, 2.0]).unwrap();
+    let entry = cache.entry_path("abc123", "m", 2);
+    std::fs::write(&entry, "not json").unwrap();
+    assert_eq!(cache.get("abc123", "m", 2), None);
+
+    let failed = cache
+        .get_or_compute("abc123", "m", Some(2), || None)
+        .unwrap();
+    assert_eq!(failed, None);
+    let recomputed = cache
+        .get_or_compute("abc123", "m", None, || Some(vec![3.0, 4.0]))
+        .unwrap();
+    assert_eq!(recomputed, Some(vec![3.0, 4.0]));
+    assert_eq!(cache.get("abc123", "m", 2), Some(vec![3.0, 4.0]));
+    std::fs::remove_dir_all(cache.dir()).unwrap();
+}
@@ -0,0 +1,516 @@
+use super::{l2_norm, MIN_EMBEDDING_NORM};
+
+use std::fmt;
+use std::path::Path;
+
+use serde::Deserialize;
+
+use crate::markup::{atx_heading, fence_marker};
+use crate::utils::extension_of;
+
+/// How text longer than `chunk_size` is reduced before it reaches the
+/// provider, so every backend sees the same input.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum LongTextStrategy {
+    /// Cut the text at `chunk_size` characters.
+    Truncate,
+    /// Embed every chunk and mean-pool the vectors.
+    #[default]
+    ChunkMean,
+    /// Keep the leading paragraphs that fit in `chunk_size` characters.
+    Head,
+}
+
+/// Overlap between consecutive chunks when none is configured, capped at
+/// a quarter of `chunk_size`.
+pub const DEFAULT_CHUNK_OVERLAP: usize = 200;
+
+/// Chunking budget for long documents, in characters.
+///
+/// Loaded from `[embedding.chunking]`, where a zero `chunk_size` or a
+/// `chunk_overlap` not below `chunk_size` is a config error.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(try_from = "RawChunkOptions")]
+pub struct ChunkOptions {
+    chunk_size: usize,
+    chunk_overlap: usize,
+    long_text_strategy: LongTextStrategy,
+}
+
+/// Invalid chunking configuration.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ChunkOptionsError {
+    ZeroChunkSize,
+    OverlapTooLarge {
+        chunk_size: usize,
+        chunk_overlap: usize,
+    },
+}
+
+impl fmt::Display for ChunkOptionsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ChunkOptionsError::ZeroChunkSize => write!(f, "chunk_size must be greater than 0"),
+            ChunkOptionsError::OverlapTooLarge {
+                chunk_size,
+                chunk_overlap,
+            } => write!(
+                f,
+                "chunk_overlap ({}) must be smaller than chunk_size ({})",
+                chunk_overlap, chunk_size
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ChunkOptionsError {}
+
+impl Default for ChunkOptions {
+    fn default() -> Self {
+        let chunk_size = 8000;
+        Self {
+            chunk_size,
+            chunk_overlap: default_overlap(chunk_size),
+            long_text_strategy: LongTextStrategy::default(),
+        }
+    }
+}
+
+fn default_overlap(chunk_size: usize) -> usize {
+    DEFAULT_CHUNK_OVERLAP.min(chunk_size / 4)
+}
+
+#[derive(Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct RawChunkOptions {
+    chunk_size: usize,
+    chunk_overlap: Option<usize>,
+    long_text_strategy: LongTextStrategy,
+}
+
+impl Default for RawChunkOptions {
+    fn default() -> Self {
+        let defaults = ChunkOptions::default();
+        Self {
+            chunk_size: defaults.chunk_size,
+            chunk_overlap: None,
+            long_text_strategy: defaults.long_text_strategy,
+        }
+    }
+}
+
+impl TryFrom<RawChunkOptions> for ChunkOptions {
+    type Error = ChunkOptionsError;
+
+    fn try_from(raw: RawChunkOptions) -> Result<Self, Self::Error> {
+        let options = ChunkOptions::new(raw.chunk_size)?.with_strategy(raw.long_text_strategy);
+        match raw.chunk_overlap {
+            Some(overlap) => options.with_overlap(overlap),
+            None => Ok(options),
+        }
+    }
+}
+
+impl ChunkOptions {
+    /// Options with the default overlap for `chunk_size`; see
+    /// [`DEFAULT_CHUNK_OVERLAP`].
+    pub fn new(chunk_size: usize) -> Result<Self, ChunkOptionsError> {
+        if chunk_size == 0 {
+            return Err(ChunkOptionsError::ZeroChunkSize);
+        }
+        Ok(Self {
+            chunk_size,
+            chunk_overlap: default_overlap(chunk_size),
+            ..Self::default()
+        })
+    }
+
+    /// Sets how many characters consecutive chunks share, which must be
+    /// smaller than `chunk_size`.
+    pub fn with_overlap(mut self, chunk_overlap: usize) -> Result<Self, ChunkOptionsError> {
+        if chunk_overlap >= self.chunk_size {
+            return Err(ChunkOptionsError::OverlapTooLarge {
+                chunk_size: self.chunk_size,
+                chunk_overlap,
+            });
+        }
+        self.chunk_overlap = chunk_overlap;
+        Ok(self)
+    }
+
+    pub fn with_strategy(mut self, strategy: LongTextStrategy) -> Self {
+        self.long_text_strategy = strategy;
+        self
+    }
+
+    pub fn chunk_size(&self) -> usize {
+        self.chunk_size
+    }
+
+    pub fn chunk_overlap(&self) -> usize {
+        self.chunk_overlap
+    }
+
+    pub fn long_text_strategy(&self) -> LongTextStrategy {
+        self.long_text_strategy
+    }
+
+    /// Turns `text` into the inputs sent to the provider.
+    ///
+    /// Text within the budget stays whole whatever the strategy, and blank
+    /// text yields nothing to embed. Only `chunk-mean` returns several
+    /// inputs; their embeddings are combined with [`mean_pool`].
+    pub fn prepare(&self, text: &str) -> Vec<String> {
+        if text.trim().is_empty() {
+            return Vec::new();
+        }
+        if text.chars().count() <= self.chunk_size {
+            return vec![text.to_string()];
+        }
+        match self.long_text_strategy {
+            LongTextStrategy::Truncate => vec![text.chars().take(self.chunk_size).collect()],
+            LongTextStrategy::ChunkMean => chunk_text(text, self),
+            LongTextStrategy::Head => vec![head_paragraphs(text, self.chunk_size)],
+        }
+    }
+
+    /// Like [`Self::prepare`], but chunks Markdown files at their headings
+    /// with [`chunk_markdown`] on the `chunk-mean` path.
+    pub fn split_for(&self, path: &Path, text: &str) -> Vec<String> {
+        let is_markdown = matches!(extension_of(path).as_deref(), Some("md" | "markdown"));
+        if is_markdown
+            && self.long_text_strategy == LongTextStrategy::ChunkMean
+            && text.chars().count() > self.chunk_size
+        {
+            return chunk_markdown(text, self);
+        }
+        self.prepare(text)
+    }
+
+    /// Embeds `text` as one document vector.
+    ///
+    /// Each input from [`Self::prepare`] goes through `embed`; a single
+    /// input's embedding is returned as is, several are combined with
+    /// [`mean_pool`]. Chunks whose embedding fails are left out, and
+    /// `None` is returned when none succeeded.
+    pub fn embed<F>(&self, text: &str, mut embed: F) -> Option<Vec<f32>>
+    where
+        F: FnMut(&str) -> Option<Vec<f32>>,
+    {
+        let mut embeddings: Vec<Vec<f32>> = self
+            .prepare(text)
+            .iter()
+            .filter_map(|chunk| embed(chunk))
+            .collect();
+        match embeddings.len() {
+            0 => None,
+            1 => embeddings.pop(),
+            _ => mean_pool(&embeddings),
+        }
+    }
+}
+
+/// Splits `text` into windows of at most `chunk_size` characters, each
+/// starting `chunk_overlap` characters before the previous one ends.
+///
+/// Text that already fits is returned as a single chunk, and blank text
+/// yields no chunk at all.
+pub fn chunk_text(text: &str, options: &ChunkOptions) -> Vec<String> {
+    if text.trim().is_empty() {
+        return Vec::new();
+    }
+    let chars: Vec<char> = text.chars().collect();
+    let step = options.chunk_size - options.chunk_overlap;
+    let mut chunks = Vec::new();
+    let mut start = 0;
+    loop {
+        let end = (start + options.chunk_size).min(chars.len());
+        chunks.push(chars[start..end].iter().collect());
+        if end == chars.len() {
+            return chunks;
+        }
+        start += step;
+    }
+}
+
+/// Splits Markdown on heading boundaries, one section per chunk.
+///
+/// Headings inside fenced code blocks are ignored. Sections longer than
+/// `chunk_size` characters fall back to [`chunk_text`].
+pub fn chunk_markdown(text: &str, options: &ChunkOptions) -> Vec<String> {
+    let mut sections: Vec<String> = Vec::new();
+    let mut current = String::new();
+    // Marker character and length of the currently open code fence.
+    let mut fence: Option<(char, usize)> = None;
+
+    for line in text.lines() {
+        match (fence, fence_marker(line)) {
+            (None, Some(opening)) => fence = Some(opening),
+            (Some((open, open_len)), Some((marker, len)))
+                if open == marker
+                    && len >= open_len
+                    && line.trim_start()[len..].trim().is_empty() =>
+            {
+                fence = None
+            }
+            _ => {}
+        }
+        if fence.is_none() && atx_heading(line).is_some() && !current.trim().is_empty() {
+            sections.push(std::mem::take(&mut current));
+        }
+        current.push_str(line);
+        current.push('\n');
+    }
+    if !current.trim().is_empty() {
+        sections.push(current);
+    }
+
+    sections
+        .into_iter()
+        .flat_map(|section| chunk_text(section.trim_end(), options))
+        .collect()
+}
+
+/// Mean-pools chunk embeddings into one L2-normalized document vector.
+///
+/// Returns `None` when there are no embeddings, their dimensions differ,
+/// or the pooled vector is degenerate.
+pub fn mean_pool(embeddings: &[Vec<f32>]) -> Option<Vec<f32>> {
+    let dims = embeddings.first()?.len();
+    if dims == 0 || embeddings.iter().any(|e| e.len() != dims) {
+        return None;
+    }
+
+    let mut pooled = vec![0.0f32; dims];
+    for embedding in embeddings {
+        for (acc, x) in pooled.iter_mut().zip(embedding) {
+            *acc += x;
+        }
+    }
+
+    let norm = l2_norm(&pooled);
+    if !norm.is_finite() || norm < MIN_EMBEDDING_NORM {
+        return None;
+    }
+    pooled.iter_mut().for_each(|x| *x /= norm);
+    Some(pooled)
+}
+
+/// Leading paragraphs of `text` that fit in `budget` characters, or a hard
+/// cut of the first paragraph when even that one is too long.
+fn head_paragraphs(text: &str, budget: usize) -> String {
+    let mut head = String::new();
+    let mut len = 0;
+    for paragraph in text.split("\n\n").filter(|p| !p.trim().is_empty()) {
+        let separator = if head.is_empty() { 0 } else { 2 };
+        let paragraph_len = paragraph.chars().count();
+        if len + separator + paragraph_len > budget {
+            break;
+        }
+        if separator > 0 {
+            head.push_str("\n\n");
+        }
+        head.push_str(paragraph);
+        len += separator + paragraph_len;
+    }
+    if head.is_empty() {
+        return text.trim_start().chars().take(budget).collect();
+    }
+    head
+}
+
+#[test]
+fn test_prepare_keeps_short_text_whole() {
+    for strategy in [
+        LongTextStrategy::Truncate,
+        LongTextStrategy::ChunkMean,
+        LongTextStrategy::Head,
+    ] {
+        let options = ChunkOptions::new(100).unwrap().with_strategy(strategy);
+        assert_eq!(
+            options.prepare("a short document"),
+            vec!["a short document"]
+        );
+        assert!(options.prepare("  \n ").is_empty());
+    }
+}
+
+#[test]
+fn test_prepare_applies_each_strategy() {
+    let text = "first paragraph\n\nsecond one\n\nthird paragraph";
+    let options = ChunkOptions::new(30).unwrap().with_overlap(0).unwrap();
+
+    assert_eq!(
+        options
+            .with_strategy(LongTextStrategy::Truncate)
+            .prepare(text),
+        vec!["first paragraph\n\nsecond one\n\nt"]
+    );
+    assert_eq!(
+        options.with_strategy(LongTextStrategy::Head).prepare(text),
+        vec!["first paragraph\n\nsecond one"]
+    );
+    assert_eq!(
+        options
+            .with_strategy(LongTextStrategy::ChunkMean)
+            .prepare(text),
+        vec!["first paragraph\n\nsecond one\n\nt", "hird paragraph"]
+    );
+}
+
+#[test]
+fn test_prepare_head_cuts_an_oversized_first_paragraph() {
+    let options = ChunkOptions::new(5)
+        .unwrap()
+        .with_strategy(LongTextStrategy::Head);
+    assert_eq!(options.prepare("abcdefghij\n\nrest"), vec!["abcde"]);
+}
+
+#[test]
+fn test_chunk_options_rejects_zero_size() {
+    assert_eq!(ChunkOptions::new(0), Err(ChunkOptionsError::ZeroChunkSize));
+    assert_eq!(
+        ChunkOptions::default().long_text_strategy(),
+        LongTextStrategy::ChunkMean
+    );
+}
+
+#[test]
+fn test_chunk_text_overlaps_windows() {
+    let options = ChunkOptions::new(4).unwrap().with_overlap(1).unwrap();
+    assert_eq!(
+        chunk_text("abcdefghij", &options),
+        vec!["abcd", "defg", "ghij"]
+    );
+    assert_eq!(chunk_text("abcdefgh", &options), vec!["abcd", "defg", "gh"]);
+    assert_eq!(
+        ChunkOptions::default().chunk_overlap(),
+        DEFAULT_CHUNK_OVERLAP
+    );
+    assert_eq!(ChunkOptions::new(100).unwrap().chunk_overlap(), 25);
+}
+
+#[test]
+fn test_chunk_options_rejects_overlap_not_below_size() {
+    assert_eq!(
+        ChunkOptions::new(10).unwrap().with_overlap(10),
+        Err(ChunkOptionsError::OverlapTooLarge {
+            chunk_size: 10,
+            chunk_overlap: 10,
+        })
+    );
+    assert!(ChunkOptions::new(10).unwrap().with_overlap(9).is_ok());
+}
+
+#[test]
+fn test_embed_pools_chunk_embeddings() {
+    let options = ChunkOptions::new(4).unwrap().with_overlap(0).unwrap();
+    let mut calls = Vec::new();
+    let embedding = options.embed("aaaabbbb", |chunk| {
+        calls.push(chunk.to_string());
+        Some(if chunk == "aaaa" {
+            vec![3.0, 0.0]
+        } else {
+            vec![0.0, 3.0]
+        })
+    });
+    assert_eq!(calls, vec!["aaaa", "bbbb"]);
+    let expected = std::f32::consts::FRAC_1_SQRT_2;
+    let embedding = embedding.unwrap();
+    assert!((embedding[0] - expected).abs() < 1e-6);
+    assert!((embedding[1] - expected).abs() < 1e-6);
+
+    // Short text is embedded once and left unnormalized.
+    assert_eq!(
+        options.embed("abc", |_| Some(vec![3.0, 4.0])),
+        Some(vec![3.0, 4.0])
+    );
+    // A failed chunk is left out; no successful chunk means no embedding.
+    let partial = options.embed("aaaabbbb", |chunk| {
+        (chunk == "bbbb").then(|| vec![0.0, 2.0])
+    });
+    assert_eq!(partial, Some(vec![0.0, 2.0]));
+    assert_eq!(options.embed("aaaabbbb", |_| None), None);
+    assert_eq!(
+        options.embed("   ", |_| panic!("blank text was embedded")),
+        None
+    );
+}
+
+#[test]
+fn test_mean_pool_normalizes() {
+    let pooled = mean_pool(&[vec![1.0, 0.0], vec![0.0, 1.0]]).unwrap();
+    let expected = std::f32::consts::FRAC_1_SQRT_2;
+    assert!((pooled[0] - expected).abs() < 1e-6);
+    assert!((pooled[1] - expected).abs() < 1e-6);
+    assert!((l2_norm(&pooled) - 1.0).abs() < 1e-6);
+}
+
+#[test]
+fn test_mean_pool_rejects_invalid_input() {
+    assert_eq!(mean_pool(&[]), None);
+    assert_eq!(mean_pool(&[vec![1.0], vec![1.0, 2.0]]), None);
+    assert_eq!(mean_pool(&[vec![1.0, 0.0], vec![-1.0, 0.0]]), None);
+}
+
+#[test]
+fn test_chunk_markdown_splits_at_headings() {
+    let doc = "Intro line\n# Title\nSome text\n## Part\nMore text\n```\n# not a heading\n```\n";
+    let chunks = chunk_markdown(doc, &ChunkOptions::new(1000).unwrap());
+    assert_eq!(
+        chunks,
+        vec![
+            "Intro line",
+            "# Title\nSome text",
+            "## Part\nMore text\n```\n# not a heading\n```",
+        ]
+    );
+}
+
+#[test]
+fn test_chunk_markdown_fence_closes_only_on_same_marker() {
+    let doc = "# A\n~~~\n```\n# inside tilde fence\n~~~\n# B\ntext\n";
+    let chunks = chunk_markdown(doc, &ChunkOptions::new(1000).unwrap());
+    assert_eq!(
+        chunks,
+        vec!["# A\n~~~\n```\n# inside tilde fence\n~~~", "# B\ntext"]
+    );
+
+    let doc = "# A\n````\n```\n# still code\n````\n# B\n";
+    let chunks = chunk_markdown(doc, &ChunkOptions::new(1000).unwrap());
+    assert_eq!(chunks, vec!["# A\n````\n```\n# still code\n````", "# B"]);
+}
+
+#[test]
+fn test_chunk_markdown_oversized_section_falls_back() {
+    let doc = format!("# Short\nok\n# Long\n{}\n", "x".repeat(50));
+    let chunks = chunk_markdown(&doc, &ChunkOptions::new(20).unwrap());
+    assert_eq!(chunks[0], "# Short\nok");
+    assert!(chunks.len() > 2);
+    assert!(chunks.iter().all(|c| c.chars().count() <= 20));
+    assert!(chunks[1].starts_with("# Long"));
+}
+
+#[test]
+fn test_split_for_uses_markdown_chunker_for_md_files() {
+    let doc = "# One\nfirst section\n# Two\nsecond section\n";
+    let options = ChunkOptions::new(30).unwrap();
+    assert_eq!(
+        options.split_for(Path::new("notes.md"), doc),
+        vec!["# One\nfirst section", "# Two\nsecond section"]
+    );
+    assert_eq!(
+        options.split_for(Path::new("notes.txt"), doc),
+        options.prepare(doc)
+    );
+    let head = options.with_strategy(LongTextStrategy::Head);
+    assert_eq!(
+        head.split_for(Path::new("notes.md"), doc),
+        head.prepare(doc)
+    );
+    assert_eq!(
+        ChunkOptions::default().split_for(Path::new("notes.md"), doc),
+        vec![doc]
+    );
+}
@@ -0,0 +1,187 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use serde::Deserialize;
+
+/// Circuit-breaker settings, loaded from `[embedding.circuit_breaker]`.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CircuitBreakerConfig {
+    /// Consecutive failures after which a server is skipped.
+    pub failure_threshold: u32,
+    /// How long a tripped server is skipped before it is probed again.
+    pub cooldown_secs: u64,
+}
+
+impl Default for CircuitBreakerConfig {
+    fn default() -> Self {
+        Self {
+            failure_threshold: 3,
+            cooldown_secs: 30,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum State {
+    Closed {
+        failures: u32,
+    },
+    Open {
+        until: Instant,
+    },
+    /// Cooldown elapsed and a single probe request is in flight. A probe
+    /// whose outcome is still unknown at `until` counts as lost.
+    HalfOpen {
+        until: Instant,
+    },
+}
+
+/// Per-server circuit breaker keyed by URL.
+///
+/// After `failure_threshold` consecutive failures a server is skipped for
+/// the cooldown. Once the cooldown has elapsed, one probe request is let
+/// through: success closes the circuit, failure opens it again. A probe
+/// never reported within another cooldown, such as one whose caller was
+/// cancelled, is treated as lost and a new probe is allowed.
+#[derive(Debug)]
+pub struct CircuitBreaker {
+    failure_threshold: u32,
+    cooldown: Duration,
+    servers: Mutex<HashMap<String, State>>,
+}
+
+impl CircuitBreaker {
+    pub fn new(config: &CircuitBreakerConfig) -> Self {
+        Self {
+            failure_threshold: config.failure_threshold.max(1),
+            cooldown: Duration::from_secs(config.cooldown_secs),
+            servers: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Whether a request may be sent to `url` now.
+    pub fn allow(&self, url: &str) -> bool {
+        self.allow_at(url, Instant::now())
+    }
+
+    pub fn record_success(&self, url: &str) {
+        self.lock()
+            .insert(url.to_string(), State::Closed { failures: 0 });
+    }
+
+    pub fn record_failure(&self, url: &str) {
+        self.record_failure_at(url, Instant::now())
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<String, State>> {
+        self.servers
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    fn allow_at(&self, url: &str, now: Instant) -> bool {
+        let mut servers = self.lock();
+        let state = servers
+            .entry(url.to_string())
+            .or_insert(State::Closed { failures: 0 });
+        match *state {
+            State::Closed { .. } => true,
+            State::Open { until } | State::HalfOpen { until } if now >= until => {
+                *state = State::HalfOpen {
+                    until: now + self.cooldown,
+                };
+                true
+            }
+            State::Open { .. } | State::HalfOpen { .. } => false,
+        }
+    }
+
+    fn record_failure_at(&self, url: &str, now: Instant) {
+        let mut servers = self.lock();
+        let state = servers
+            .entry(url.to_string())
+            .or_insert(State::Closed { failures: 0 });
+        *state = match *state {
+            State::Closed { failures } if failures + 1 < self.failure_threshold => State::Closed {
+                failures: failures + 1,
+            },
+            _ => State::Open {
+                until: now + self.cooldown,
+            },
+        };
+    }
+}
+
+#[cfg(test)]
+fn test_breaker() -> CircuitBreaker {
+    CircuitBreaker::new(&CircuitBreakerConfig {
+        failure_threshold: 2,
+        cooldown_secs: 10,
+    })
+}
+
+#[test]
+fn test_circuit_opens_after_consecutive_failures() {
+    let breaker = test_breaker();
+    let now = Instant::now();
+    assert!(breaker.allow_at("http://a", now));
+    breaker.record_failure_at("http://a", now);
+    assert!(breaker.allow_at("http://a", now));
+    breaker.record_failure_at("http://a", now);
+    assert!(!breaker.allow_at("http://a", now));
+    assert!(breaker.allow_at("http://b", now));
+}
+
+#[test]
+fn test_circuit_success_resets_failures() {
+    let breaker = test_breaker();
+    let now = Instant::now();
+    breaker.record_failure_at("http://a", now);
+    breaker.record_success("http://a");
+    breaker.record_failure_at("http://a", now);
+    assert!(breaker.allow_at("http://a", now));
+}
+
+#[test]
+fn test_circuit_half_open_probe() {
+    let breaker = test_breaker();
+    let now = Instant::now();
+    breaker.record_failure_at("http://a", now);
+    breaker.record_failure_at("http://a", now);
+
+    let later = now + Duration::from_secs(10);
+    assert!(breaker.allow_at("http://a", later));
+    // Only one probe at a time while half-open.
+    assert!(!breaker.allow_at("http://a", later));
+
+    // A failed probe reopens the circuit for another cooldown.
+    breaker.record_failure_at("http://a", later);
+    assert!(!breaker.allow_at("http://a", later + Duration::from_secs(5)));
+
+    let much_later = later + Duration::from_secs(10);
+    assert!(breaker.allow_at("http://a", much_later));
+    breaker.record_success("http://a");
+    assert!(breaker.allow_at("http://a", much_later));
+    assert!(breaker.allow_at("http://a", much_later));
+}
+
+#[test]
+fn test_circuit_lost_probe_allows_another() {
+    let breaker = test_breaker();
+    let now = Instant::now();
+    breaker.record_failure_at("http://a", now);
+    breaker.record_failure_at("http://a", now);
+
+    // The probe is let through but its result is never recorded.
+    let probe = now + Duration::from_secs(10);
+    assert!(breaker.allow_at("http://a", probe));
+    assert!(!breaker.allow_at("http://a", probe + Duration::from_secs(9)));
+
+    let retry = probe + Duration::from_secs(10);
+    assert!(breaker.allow_at("http://a", retry));
+    assert!(!breaker.allow_at("http://a", retry));
+    breaker.record_success("http://a");
+    assert!(breaker.allow_at("http://a", retry));
+}
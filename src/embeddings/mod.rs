@@ -0,0 +1,671 @@
+pub mod balance;
+pub mod cache;
+pub mod chunk;
+pub mod circuit;
+pub mod quantize;
+pub mod rate_limit;
+pub mod retry;
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::num::NonZeroU32;
+use std::path::{Path, PathBuf};
+
+use serde::Deserialize;
+
+use crate::tagging::{normalize_tag, UNKNOWN_TAG};
+
+use self::cache::EmbeddingCache;
+use self::chunk::ChunkOptions;
+use self::circuit::CircuitBreakerConfig;
+use self::quantize::{quantize, NonFiniteEmbedding, StoredEmbedding};
+use self::rate_limit::{MaxRps, RateLimiter};
+use self::retry::RetryConfig;
+use crate::utils::{ExtensionFilter, StatePaths};
+
+/// Model used when no category-specific model applies.
+pub const DEFAULT_EMBEDDING_MODEL: &str = "nomic-embed-text";
+
+/// Text embedded at startup to learn the provider's dimension.
+pub const DIMENSION_PROBE: &str = "cognify dimension probe";
+
+/// L2 norm below which an embedding is treated as a zero vector.
+pub const MIN_EMBEDDING_NORM: f32 = 1e-6;
+
+/// What the embedding input of a file is built from.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EmbeddingSource {
+    /// Extracted text.
+    #[default]
+    Text,
+    /// Extracted text followed by the file's tags.
+    #[serde(rename = "text+tags")]
+    TextAndTags,
+    /// The file's tags alone, for tag-centric clustering.
+    Tags,
+    /// The file name alone.
+    Filename,
+}
+
+/// Embedding settings, loaded from the `[embedding]` section of the config.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct EmbeddingConfig {
+    /// Abort instead of warning when the index holds vectors of another
+    /// dimension than the provider returns.
+    pub strict_dims: bool,
+    /// Extensions whose files are indexed with metadata and tags only,
+    /// without calling the provider.
+    pub skip_extensions: Vec<String>,
+    /// Model used for files whose tags have no entry in `model_by_category`.
+    pub model: String,
+    /// Model to use instead of `model` for files in a category, e.g.
+    /// `code = "nomic-embed-code"`.
+    pub model_by_category: BTreeMap<String, String>,
+    /// Treat all-zero and near-zero vectors as a failed embedding.
+    pub reject_zero_vectors: bool,
+    /// How long texts are cut down before they reach the provider.
+    pub chunking: ChunkOptions,
+    /// What the embedding input is built from; see [`Self::content_for`].
+    pub source: EmbeddingSource,
+    /// Prefix the embedding input with the file's path under the walk
+    /// root, such as `Clients / Acme / invoice.pdf` (off by default).
+    pub include_path_context: bool,
+    /// Dimension the provider is expected to return. Unset, it is taken
+    /// from the first accepted embedding.
+    pub dimension: Option<usize>,
+    /// Fail on embeddings of another length than `dimension` instead of
+    /// switching to the new length.
+    pub dimension_strict: bool,
+    /// Embed [`DIMENSION_PROBE`] at startup to learn the dimension before
+    /// the first file; see [`Self::detect_dimension`].
+    pub detect_dims: bool,
+    /// Cap on provider calls per second, shared by every worker; `0`
+    /// disables it.
+    pub max_rps: MaxRps,
+    /// Calls allowed back to back before `max_rps` spacing applies.
+    pub burst: NonZeroU32,
+    /// Store embeddings as `i8` with a scale instead of `f32`, trading a
+    /// little recall for a quarter of the index size; see
+    /// [`Self::for_storage`].
+    pub quantize: bool,
+    /// Directory of the embedding cache; relative paths are resolved
+    /// against the base directory. Unset, the state directory's
+    /// `cache/embeddings` is used; see [`Self::cache`].
+    pub cache_dir: Option<PathBuf>,
+    /// Retries of provider calls that failed transiently; see
+    /// [`retry::retry`].
+    pub retry: RetryConfig,
+    /// When to stop sending requests to a failing server; see
+    /// [`circuit::CircuitBreaker`].
+    pub circuit_breaker: CircuitBreakerConfig,
+}
+
+impl Default for EmbeddingConfig {
+    fn default() -> Self {
+        Self {
+            strict_dims: false,
+            skip_extensions: Vec::new(),
+            model: DEFAULT_EMBEDDING_MODEL.to_string(),
+            model_by_category: BTreeMap::new(),
+            reject_zero_vectors: true,
+            chunking: ChunkOptions::default(),
+            source: EmbeddingSource::default(),
+            include_path_context: false,
+            dimension: None,
+            dimension_strict: false,
+            detect_dims: true,
+            max_rps: MaxRps::UNLIMITED,
+            burst: NonZeroU32::MIN,
+            quantize: false,
+            cache_dir: None,
+            retry: RetryConfig::default(),
+            circuit_breaker: CircuitBreakerConfig::default(),
+        }
+    }
+}
+
+impl EmbeddingConfig {
+    /// Picks the embedding model for a file from its tags.
+    ///
+    /// Tags are tried in order, so the primary tag wins over later ones.
+    /// Files without a mapped tag use `model`.
+    pub fn model_for(&self, tags: &[String]) -> &str {
+        tags.iter()
+            .find_map(|tag| {
+                let tag = normalize_tag(tag);
+                self.model_by_category
+                    .iter()
+                    .find(|(category, _)| normalize_tag(category) == tag)
+                    .map(|(_, model)| model.as_str())
+            })
+            .unwrap_or(&self.model)
+    }
+
+    /// Rate limiter to wrap provider calls in, or `None` without `max_rps`.
+    pub fn rate_limiter(&self) -> Option<RateLimiter> {
+        RateLimiter::new(self.max_rps, self.burst)
+    }
+
+    /// Whether the file at `path` should be sent to the provider.
+    pub fn should_embed(&self, path: &Path) -> bool {
+        ExtensionFilter::new(&[], &self.skip_extensions).matches(path)
+    }
+
+    /// Builds the text sent to the provider for the file at `path`, found
+    /// by a walk of `root`, following `source`.
+    ///
+    /// Whenever the preferred input is empty, such as blank extracted text
+    /// or a file with no tag but the unknown placeholder, the file name and
+    /// its tags are used instead. With `include_path_context`, the path
+    /// relative to `root` is prepended; a path outside `root` contributes
+    /// its file name only, so no home directory reaches the provider.
+    pub fn content_for(&self, text: &str, path: &Path, root: &Path, tags: &[String]) -> String {
+        let content = self.source_content(text, path, tags);
+        if !self.include_path_context {
+            return content;
+        }
+        let relative = path
+            .strip_prefix(root)
+            .ok()
+            .filter(|relative| !relative.as_os_str().is_empty())
+            .or_else(|| path.file_name().map(Path::new));
+        let Some(relative) = relative else {
+            return content;
+        };
+        let preamble: Vec<String> = relative
+            .components()
+            .filter_map(|c| match c {
+                std::path::Component::Normal(part) => Some(part.to_string_lossy().into_owned()),
+                _ => None,
+            })
+            .collect();
+        format!("Path: {}\n\n{}", preamble.join(" / "), content)
+    }
+
+    fn source_content(&self, text: &str, path: &Path, tags: &[String]) -> String {
+        let tags: Vec<&str> = tags
+            .iter()
+            .map(|t| t.as_str())
+            .filter(|t| !t.trim().is_empty() && *t != UNKNOWN_TAG)
+            .collect();
+        let text = text.trim();
+        let file_name = path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+
+        let content = match self.source {
+            EmbeddingSource::Text => text.to_string(),
+            EmbeddingSource::TextAndTags if text.is_empty() || tags.is_empty() => text.to_string(),
+            EmbeddingSource::TextAndTags => format!("{}\n\nTags: {}", text, tags.join(", ")),
+            EmbeddingSource::Tags => tags.join(", "),
+            EmbeddingSource::Filename => file_name.clone(),
+        };
+        if !content.is_empty() {
+            return content;
+        }
+        if tags.is_empty() {
+            file_name
+        } else {
+            format!("{}\nTags: {}", file_name, tags.join(", "))
+        }
+    }
+
+    /// Checks an embedding returned by the provider before it is stored.
+    ///
+    /// Empty vectors and vectors holding NaN or infinite values are always
+    /// rejected; vectors whose norm is below [`MIN_EMBEDDING_NORM`] are
+    /// rejected when `reject_zero_vectors` is set. A rejected embedding is
+    /// handled like a provider failure and returns `Ok(None)`.
+    ///
+    /// A valid embedding whose length differs from `dimension` is an error
+    /// when `dimension_strict` is set; otherwise `dimension` is updated to
+    /// the new length.
+    pub fn accept(&mut self, embedding: Vec<f32>) -> Result<Option<Vec<f32>>, DimensionMismatch> {
+        if embedding.is_empty() || embedding.iter().any(|x| !x.is_finite()) {
+            return Ok(None);
+        }
+        if self.reject_zero_vectors && l2_norm(&embedding) < MIN_EMBEDDING_NORM {
+            return Ok(None);
+        }
+        match self.dimension {
+            Some(expected) if expected != embedding.len() && self.dimension_strict => {
+                return Err(DimensionMismatch {
+                    expected,
+                    actual: embedding.len(),
+                })
+            }
+            _ => self.dimension = Some(embedding.len()),
+        }
+        Ok(Some(embedding))
+    }
+
+    /// Learns the provider's dimension by embedding [`DIMENSION_PROBE`]
+    /// before any file is processed.
+    ///
+    /// The probe goes through [`Self::accept`], so it sets `dimension` or,
+    /// with `dimension_strict`, fails when the server disagrees with the
+    /// configured one. Returns the detected dimension for the caller to
+    /// report, or `None` when `detect_dims` is off or the probe failed.
+    pub fn detect_dimension<F>(&mut self, embed: F) -> Result<Option<usize>, DimensionMismatch>
+    where
+        F: FnOnce(&str) -> Option<Vec<f32>>,
+    {
+        if !self.detect_dims {
+            return Ok(None);
+        }
+        let Some(embedding) = embed(DIMENSION_PROBE) else {
+            return Ok(None);
+        };
+        Ok(self.accept(embedding)?.map(|embedding| embedding.len()))
+    }
+
+    /// Compares the provider's embedding dimension with the one recorded in
+    /// the index.
+    ///
+    /// A mismatch is returned for the caller to warn about, or as an error
+    /// when `strict_dims` is set. An index without a recorded dimension
+    /// accepts any provider.
+    pub fn reconcile_index_dims(
+        &self,
+        index_dims: Option<usize>,
+        provider_dims: usize,
+    ) -> Result<Option<DimensionMismatch>, DimensionMismatch> {
+        let mismatch = index_dims
+            .filter(|dims| *dims != provider_dims)
+            .map(|expected| DimensionMismatch {
+                expected,
+                actual: provider_dims,
+            });
+        match mismatch {
+            Some(mismatch) if self.strict_dims => Err(mismatch),
+            other => Ok(other),
+        }
+    }
+
+    /// Opens the embedding cache for the tree rooted at `base`.
+    pub fn cache(&self, base: &Path, state: &StatePaths) -> EmbeddingCache {
+        EmbeddingCache::new(match &self.cache_dir {
+            Some(dir) => base.join(dir),
+            None => state.embeddings_cache_dir(),
+        })
+    }
+
+    /// Converts an accepted embedding to the form written to the index,
+    /// quantized when `quantize` is set.
+    pub fn for_storage(&self, embedding: Vec<f32>) -> Result<StoredEmbedding, NonFiniteEmbedding> {
+        if !self.quantize {
+            return Ok(StoredEmbedding::Float(embedding));
+        }
+        quantize(&embedding).map(StoredEmbedding::Quantized)
+    }
+}
+
+fn l2_norm(embedding: &[f32]) -> f32 {
+    embedding.iter().map(|x| x * x).sum::<f32>().sqrt()
+}
+
+/// Cosine similarity of two embeddings, in `[-1, 1]`.
+///
+/// Returns `None` when the lengths differ or either vector is empty,
+/// degenerate or not finite, since no meaningful angle exists then.
+pub fn cosine_similarity(a: &[f32], b: &[f32]) -> Option<f32> {
+    if a.is_empty() || a.len() != b.len() {
+        return None;
+    }
+    let norms = l2_norm(a) * l2_norm(b);
+    if !norms.is_finite() || norms < MIN_EMBEDDING_NORM * MIN_EMBEDDING_NORM {
+        return None;
+    }
+    let dot: f32 = a.iter().zip(b).map(|(x, y)| x * y).sum();
+    Some((dot / norms).clamp(-1.0, 1.0))
+}
+
+/// Embeddings of one dimension where another was expected.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DimensionMismatch {
+    pub expected: usize,
+    pub actual: usize,
+}
+
+impl fmt::Display for DimensionMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "embedding dimension mismatch: expected {}, got {}",
+            self.expected, self.actual
+        )
+    }
+}
+
+impl std::error::Error for DimensionMismatch {}
+
+#[test]
+fn test_reconcile_index_dims_matching_or_unrecorded() {
+    let config = EmbeddingConfig {
+        strict_dims: true,
+        ..EmbeddingConfig::default()
+    };
+    assert_eq!(config.reconcile_index_dims(Some(768), 768), Ok(None));
+    assert_eq!(config.reconcile_index_dims(None, 1024), Ok(None));
+}
+
+#[test]
+fn test_reconcile_index_dims_mismatch() {
+    let mismatch = DimensionMismatch {
+        expected: 768,
+        actual: 4096,
+    };
+    let lax = EmbeddingConfig::default();
+    assert_eq!(
+        lax.reconcile_index_dims(Some(768), 4096),
+        Ok(Some(mismatch))
+    );
+
+    let strict = EmbeddingConfig {
+        strict_dims: true,
+        ..EmbeddingConfig::default()
+    };
+    assert_eq!(strict.reconcile_index_dims(Some(768), 4096), Err(mismatch));
+    assert_eq!(
+        mismatch.to_string(),
+        "embedding dimension mismatch: expected 768, got 4096"
+    );
+}
+
+#[test]
+fn test_should_embed_skips_configured_extensions() {
+    let config = EmbeddingConfig {
+        skip_extensions: vec!["ZIP".to_string(), ".iso".to_string()],
+        ..EmbeddingConfig::default()
+    };
+    let embedded: Vec<&str> = ["backup.zip", "notes.md", "disk.ISO", "README"]
+        .into_iter()
+        .filter(|file| config.should_embed(Path::new(file)))
+        .collect();
+    assert_eq!(embedded, vec!["notes.md", "README"]);
+    assert!(EmbeddingConfig::default().should_embed(Path::new("backup.zip")));
+}
+
+#[test]
+fn test_model_for_routes_by_category() {
+    let config = EmbeddingConfig {
+        model_by_category: BTreeMap::from([
+            ("code".to_string(), "nomic-embed-code".to_string()),
+            ("Legal".to_string(), "legal-embed".to_string()),
+        ]),
+        ..EmbeddingConfig::default()
+    };
+    let code = vec!["code".to_string(), "rust".to_string()];
+    let document = vec!["financial".to_string(), "invoice".to_string()];
+    let mixed = vec!["legal".to_string(), "code".to_string()];
+
+    assert_eq!(config.model_for(&code), "nomic-embed-code");
+    assert_eq!(config.model_for(&document), DEFAULT_EMBEDDING_MODEL);
+    assert_eq!(config.model_for(&mixed), "legal-embed");
+    assert_eq!(config.model_for(&[]), DEFAULT_EMBEDDING_MODEL);
+}
+
+#[test]
+fn test_accept_rejects_degenerate_vectors() {
+    let mut config = EmbeddingConfig::default();
+    assert_eq!(config.accept(vec![0.6, 0.8]), Ok(Some(vec![0.6, 0.8])));
+    assert_eq!(config.accept(vec![0.0; 2]), Ok(None));
+    assert_eq!(config.accept(vec![1e-9, -1e-9]), Ok(None));
+    assert_eq!(config.accept(Vec::new()), Ok(None));
+
+    let mut lax = EmbeddingConfig {
+        reject_zero_vectors: false,
+        ..EmbeddingConfig::default()
+    };
+    assert_eq!(lax.accept(vec![0.0; 4]), Ok(Some(vec![0.0; 4])));
+}
+
+#[test]
+fn test_accept_rejects_non_finite_values() {
+    let lax = EmbeddingConfig {
+        reject_zero_vectors: false,
+        ..EmbeddingConfig::default()
+    };
+    for mut config in [EmbeddingConfig::default(), lax] {
+        assert_eq!(config.accept(vec![0.5, f32::NAN]), Ok(None));
+        assert_eq!(config.accept(vec![f32::NAN; 3]), Ok(None));
+        assert_eq!(config.accept(vec![f32::INFINITY, 0.1]), Ok(None));
+        assert_eq!(config.dimension, None);
+    }
+}
+
+#[test]
+fn test_content_for_follows_source() {
+    let path = Path::new("/home/me/docs/invoice_2024.pdf");
+    let tags = vec!["financial".to_string(), "invoice".to_string()];
+    let content = |source, text: &str, tags: &[String]| {
+        let config = EmbeddingConfig {
+            source,
+            ..EmbeddingConfig::default()
+        };
+        config.content_for(text, path, Path::new("/home/me"), tags)
+    };
+
+    assert_eq!(
+        content(EmbeddingSource::Text, "Total due", &tags),
+        "Total due"
+    );
+    assert_eq!(
+        content(EmbeddingSource::TextAndTags, "Total due", &tags),
+        "Total due\n\nTags: financial, invoice"
+    );
+    assert_eq!(
+        content(EmbeddingSource::Tags, "Total due", &tags),
+        "financial, invoice"
+    );
+    assert_eq!(
+        content(EmbeddingSource::Filename, "Total due", &tags),
+        "invoice_2024.pdf"
+    );
+}
+
+#[test]
+fn test_content_for_falls_back_to_file_name() {
+    let path = Path::new("scan_0042.png");
+    let tags = vec!["image".to_string()];
+    let unknown = vec![UNKNOWN_TAG.to_string()];
+    let content = |source, text: &str, tags: &[String]| {
+        let config = EmbeddingConfig {
+            source,
+            ..EmbeddingConfig::default()
+        };
+        config.content_for(text, path, Path::new("/home/me"), tags)
+    };
+
+    assert_eq!(
+        content(EmbeddingSource::Text, "  ", &tags),
+        "scan_0042.png\nTags: image"
+    );
+    assert_eq!(
+        content(EmbeddingSource::TextAndTags, "", &unknown),
+        "scan_0042.png"
+    );
+    assert_eq!(
+        content(EmbeddingSource::TextAndTags, "Receipt", &[]),
+        "Receipt"
+    );
+    assert_eq!(
+        content(EmbeddingSource::Tags, "Receipt", &[]),
+        "scan_0042.png"
+    );
+    assert_eq!(
+        content(EmbeddingSource::Tags, "Receipt", &unknown),
+        "scan_0042.png"
+    );
+}
+
+#[test]
+fn test_accept_checks_configured_dimension() {
+    // Stands in for a server configured for 768 dimensions that returns 1024.
+    let provider = |_: &str| vec![0.5f32; 1024];
+
+    let mut strict = EmbeddingConfig {
+        dimension: Some(768),
+        dimension_strict: true,
+        ..EmbeddingConfig::default()
+    };
+    let err = strict.accept(provider("text")).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "embedding dimension mismatch: expected 768, got 1024"
+    );
+    assert_eq!(strict.dimension, Some(768));
+
+    let mut lax = EmbeddingConfig {
+        dimension: Some(768),
+        ..EmbeddingConfig::default()
+    };
+    assert_eq!(
+        lax.accept(provider("text")).unwrap().map(|e| e.len()),
+        Some(1024)
+    );
+    assert_eq!(lax.dimension, Some(1024));
+
+    let mut unset = EmbeddingConfig {
+        dimension_strict: true,
+        ..EmbeddingConfig::default()
+    };
+    assert!(unset.accept(vec![1.0; 3]).is_ok());
+    assert_eq!(unset.dimension, Some(3));
+    assert!(unset.accept(provider("text")).is_err());
+}
+
+#[test]
+fn test_detect_dimension_applies_before_processing() {
+    let mut probed = Vec::new();
+    let mut config = EmbeddingConfig {
+        dimension: Some(768),
+        ..EmbeddingConfig::default()
+    };
+    let detected = config.detect_dimension(|text| {
+        probed.push(text.to_string());
+        Some(vec![0.1; 1024])
+    });
+    assert_eq!(detected, Ok(Some(1024)));
+    assert_eq!(probed, vec![DIMENSION_PROBE]);
+    assert_eq!(config.dimension, Some(1024));
+
+    // The first file is then checked against the detected dimension.
+    config.dimension_strict = true;
+    assert!(config.accept(vec![0.1; 1024]).unwrap().is_some());
+    assert!(config.accept(vec![0.1; 768]).is_err());
+}
+
+#[test]
+fn test_detect_dimension_skippable_and_strict() {
+    let mut skipped = EmbeddingConfig {
+        detect_dims: false,
+        ..EmbeddingConfig::default()
+    };
+    let detected = skipped.detect_dimension(|_| panic!("probe must not run"));
+    assert_eq!(detected, Ok(None));
+    assert_eq!(skipped.dimension, None);
+
+    let mut failed = EmbeddingConfig::default();
+    assert_eq!(failed.detect_dimension(|_| None), Ok(None));
+
+    let mut strict = EmbeddingConfig {
+        dimension: Some(768),
+        dimension_strict: true,
+        ..EmbeddingConfig::default()
+    };
+    assert_eq!(
+        strict.detect_dimension(|_| Some(vec![0.1; 384])),
+        Err(DimensionMismatch {
+            expected: 768,
+            actual: 384
+        })
+    );
+}
+
+#[test]
+fn test_content_for_includes_path_context() {
+    let root = Path::new("/home/me/archive");
+    let path = root.join("Clients/Acme/invoice_2024.pdf");
+    let tags = vec!["invoice".to_string()];
+    let mut config = EmbeddingConfig::default();
+
+    assert_eq!(
+        config.content_for("Total due", &path, root, &tags),
+        "Total due"
+    );
+
+    config.include_path_context = true;
+    assert_eq!(
+        config.content_for("Total due", &path, root, &tags),
+        "Path: Clients / Acme / invoice_2024.pdf\n\nTotal due"
+    );
+    assert_eq!(
+        config.content_for("", &path, root, &tags),
+        "Path: Clients / Acme / invoice_2024.pdf\n\ninvoice_2024.pdf\nTags: invoice"
+    );
+
+    let outside = Path::new("/home/me/Downloads/scan.pdf");
+    let content = config.content_for("Scanned", outside, root, &tags);
+    assert_eq!(content, "Path: scan.pdf\n\nScanned");
+    assert!(!content.contains("/home/me"));
+}
+
+#[test]
+fn test_for_storage_quantizes_when_enabled() {
+    let embedding = vec![0.5, -1.0, 0.25];
+    let config = EmbeddingConfig::default();
+    assert_eq!(
+        config.for_storage(embedding.clone()),
+        Ok(StoredEmbedding::Float(embedding.clone()))
+    );
+
+    let config = EmbeddingConfig {
+        quantize: true,
+        ..EmbeddingConfig::default()
+    };
+    let stored = config.for_storage(embedding.clone()).unwrap();
+    let StoredEmbedding::Quantized(quantized) = &stored else {
+        panic!("expected a quantized embedding, got {:?}", stored);
+    };
+    assert_eq!(quantized.values, vec![64, -127, 32]);
+    for (a, b) in embedding.iter().zip(stored.to_f32()) {
+        assert!((a - b).abs() <= quantized.scale / 2.0 + f32::EPSILON);
+    }
+    assert_eq!(config.for_storage(vec![f32::NAN]), Err(NonFiniteEmbedding));
+}
+
+#[test]
+fn test_embedding_cache_location() {
+    let base = Path::new("/data/docs");
+    let state = StatePaths::new(base, None);
+    let config = EmbeddingConfig::default();
+    assert_eq!(
+        config.cache(base, &state).dir(),
+        Path::new("/data/docs/.cognifs/cache/embeddings")
+    );
+
+    let config = EmbeddingConfig {
+        cache_dir: Some(PathBuf::from("/home/me/.cache/cognifs/embeddings")),
+        ..EmbeddingConfig::default()
+    };
+    assert_eq!(
+        config.cache(base, &state).dir(),
+        Path::new("/home/me/.cache/cognifs/embeddings")
+    );
+}
+
+#[test]
+fn test_cosine_similarity() {
+    assert_eq!(cosine_similarity(&[1.0, 0.0], &[2.0, 0.0]), Some(1.0));
+    assert_eq!(cosine_similarity(&[1.0, 0.0], &[0.0, 3.0]), Some(0.0));
+    assert_eq!(cosine_similarity(&[1.0, 1.0], &[-1.0, -1.0]), Some(-1.0));
+
+    assert_eq!(cosine_similarity(&[1.0], &[1.0, 0.0]), None);
+    assert_eq!(cosine_similarity(&[], &[]), None);
+    assert_eq!(cosine_similarity(&[0.0, 0.0], &[1.0, 0.0]), None);
+    assert_eq!(cosine_similarity(&[f32::NAN, 0.0], &[1.0, 0.0]), None);
+}
@@ -0,0 +1,111 @@
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+/// An embedding stored as `i8` values with a single scale factor.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct QuantizedEmbedding {
+    pub scale: f32,
+    pub values: Vec<i8>,
+}
+
+/// Scalar-quantizes an `f32` vector to `i8`.
+///
+/// The scale maps the largest absolute component to 127, so the round-trip
+/// error per component is at most `scale / 2`. Vectors holding NaN or
+/// infinite values have no meaningful scale and are rejected.
+pub fn quantize(embedding: &[f32]) -> Result<QuantizedEmbedding, NonFiniteEmbedding> {
+    if embedding.iter().any(|x| !x.is_finite()) {
+        return Err(NonFiniteEmbedding);
+    }
+    let max_abs = embedding.iter().fold(0.0f32, |m, x| m.max(x.abs()));
+    if max_abs == 0.0 {
+        return Ok(QuantizedEmbedding {
+            scale: 0.0,
+            values: vec![0; embedding.len()],
+        });
+    }
+
+    let scale = max_abs / 127.0;
+    let values = embedding
+        .iter()
+        .map(|x| (x / scale).round().clamp(-127.0, 127.0) as i8)
+        .collect();
+    Ok(QuantizedEmbedding { scale, values })
+}
+
+/// Restores an approximate `f32` vector from its quantized form.
+pub fn dequantize(quantized: &QuantizedEmbedding) -> Vec<f32> {
+    quantized
+        .values
+        .iter()
+        .map(|v| *v as f32 * quantized.scale)
+        .collect()
+}
+
+/// An embedding as written to the index; see
+/// [`super::EmbeddingConfig::for_storage`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum StoredEmbedding {
+    Float(Vec<f32>),
+    Quantized(QuantizedEmbedding),
+}
+
+impl StoredEmbedding {
+    /// The vector to use for local similarity, dequantized if needed.
+    pub fn to_f32(&self) -> Vec<f32> {
+        match self {
+            StoredEmbedding::Float(values) => values.clone(),
+            StoredEmbedding::Quantized(quantized) => dequantize(quantized),
+        }
+    }
+}
+
+/// An embedding holding NaN or infinite values.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NonFiniteEmbedding;
+
+impl fmt::Display for NonFiniteEmbedding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "embedding holds NaN or infinite values")
+    }
+}
+
+impl std::error::Error for NonFiniteEmbedding {}
+
+#[test]
+fn test_quantize_round_trip_within_tolerance() {
+    let embedding: Vec<f32> = (0..64).map(|i| ((i as f32) * 0.37).sin()).collect();
+    let quantized = quantize(&embedding).unwrap();
+    let restored = dequantize(&quantized);
+
+    assert_eq!(restored.len(), embedding.len());
+    for (a, b) in embedding.iter().zip(&restored) {
+        assert!((a - b).abs() <= quantized.scale / 2.0 + f32::EPSILON);
+    }
+}
+
+#[test]
+fn test_quantize_preserves_cosine_similarity() {
+    let a: Vec<f32> = (0..128).map(|i| ((i as f32) * 0.11).cos()).collect();
+    let b: Vec<f32> = (0..128).map(|i| ((i as f32) * 0.13).cos()).collect();
+    let exact = super::cosine_similarity(&a, &b).unwrap();
+    let approx = super::cosine_similarity(
+        &dequantize(&quantize(&a).unwrap()),
+        &dequantize(&quantize(&b).unwrap()),
+    )
+    .unwrap();
+    assert!((exact - approx).abs() < 0.01, "{} vs {}", exact, approx);
+}
+
+#[test]
+fn test_quantize_zero_and_non_finite_vectors() {
+    let quantized = quantize(&[0.0, 0.0, 0.0]).unwrap();
+    assert_eq!(quantized.scale, 0.0);
+    assert_eq!(dequantize(&quantized), vec![0.0, 0.0, 0.0]);
+
+    assert_eq!(quantize(&[1.0, f32::NAN]), Err(NonFiniteEmbedding));
+    assert_eq!(quantize(&[f32::INFINITY, 0.5]), Err(NonFiniteEmbedding));
+    assert_eq!(quantize(&[f32::NEG_INFINITY]), Err(NonFiniteEmbedding));
+}
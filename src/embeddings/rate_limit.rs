@@ -0,0 +1,181 @@
+use std::fmt;
+use std::num::NonZeroU32;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use serde::Deserialize;
+
+/// Requests-per-second cap on embedding calls; zero means no cap.
+///
+/// Loaded from `embedding.max_rps`. Negative, non-finite and out-of-range
+/// rates are config errors, so a typo never silently disables the limit.
+#[derive(Debug, Clone, Copy, Default, PartialEq, PartialOrd, Deserialize)]
+#[serde(try_from = "f64")]
+pub struct MaxRps(f64);
+
+impl MaxRps {
+    /// No cap on the request rate.
+    pub const UNLIMITED: MaxRps = MaxRps(0.0);
+    /// Slowest accepted rate: one call every 1000 seconds.
+    pub const MIN: f64 = 0.001;
+    /// Fastest accepted rate.
+    pub const MAX: f64 = 1_000_000.0;
+
+    pub fn new(rps: f64) -> Result<Self, MaxRpsError> {
+        if rps == 0.0 {
+            return Ok(Self::UNLIMITED);
+        }
+        if !rps.is_finite() || !(Self::MIN..=Self::MAX).contains(&rps) {
+            return Err(MaxRpsError(rps));
+        }
+        Ok(Self(rps))
+    }
+
+    pub fn get(self) -> f64 {
+        self.0
+    }
+
+    pub fn is_unlimited(self) -> bool {
+        self.0 == 0.0
+    }
+}
+
+impl TryFrom<f64> for MaxRps {
+    type Error = MaxRpsError;
+
+    fn try_from(rps: f64) -> Result<Self, Self::Error> {
+        Self::new(rps)
+    }
+}
+
+/// A `max_rps` outside `0` or `MaxRps::MIN..=MaxRps::MAX`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MaxRpsError(pub f64);
+
+impl fmt::Display for MaxRpsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "max_rps must be 0 (unlimited) or between {} and {}, got {}",
+            MaxRps::MIN,
+            MaxRps::MAX,
+            self.0
+        )
+    }
+}
+
+impl std::error::Error for MaxRpsError {}
+
+/// Token-bucket cap on embedding calls per second.
+///
+/// The bucket holds up to `burst` tokens and refills at `max_rps` tokens per
+/// second. It never sleeps while holding its lock: [`RateLimiter::reserve`]
+/// takes a token and returns how long the caller must wait for it, so it
+/// can be awaited with any runtime and combined with a concurrency
+/// semaphore without risk of deadlock.
+#[derive(Debug)]
+pub struct RateLimiter {
+    interval: Duration,
+    tolerance: Duration,
+    // Time at which the bucket would be full again if no more calls came in.
+    full_at: Mutex<Option<Instant>>,
+}
+
+impl RateLimiter {
+    /// Creates a limiter allowing `max_rps` calls per second on average,
+    /// with bursts of up to `burst` calls, or `None` when unlimited.
+    pub fn new(max_rps: MaxRps, burst: NonZeroU32) -> Option<Self> {
+        if max_rps.is_unlimited() {
+            return None;
+        }
+        let interval = Duration::from_secs_f64(1.0 / max_rps.get());
+        let tolerance = interval
+            .checked_mul(burst.get() - 1)
+            .unwrap_or(Duration::MAX);
+        Some(Self {
+            interval,
+            tolerance,
+            full_at: Mutex::new(None),
+        })
+    }
+
+    /// Takes a token and returns how long to wait before using it.
+    pub fn reserve(&self) -> Duration {
+        self.reserve_at(Instant::now())
+    }
+
+    fn reserve_at(&self, now: Instant) -> Duration {
+        let mut full_at = self
+            .full_at
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        let start = match *full_at {
+            Some(t) if t > now => t,
+            _ => now,
+        };
+        *full_at = Some(start + self.interval);
+        (start - now).saturating_sub(self.tolerance)
+    }
+}
+
+#[test]
+fn test_max_rps_rejects_unusable_rates() {
+    for rps in [-1.0, f64::NAN, f64::INFINITY, 1e-300, 1e300] {
+        assert_eq!(
+            MaxRps::new(rps).map_err(|e| e.0.to_bits()),
+            Err(rps.to_bits())
+        );
+    }
+    assert!(MaxRps::new(0.0).unwrap().is_unlimited());
+    assert_eq!(MaxRps::new(2.5).unwrap().get(), 2.5);
+
+    let one = NonZeroU32::MIN;
+    assert!(RateLimiter::new(MaxRps::UNLIMITED, one).is_none());
+    assert!(RateLimiter::new(MaxRps::new(MaxRps::MIN).unwrap(), NonZeroU32::MAX).is_some());
+}
+
+#[test]
+fn test_rate_limiter_spaces_reservations() {
+    let limiter = RateLimiter::new(MaxRps::new(10.0).unwrap(), NonZeroU32::MIN).unwrap();
+    let now = Instant::now();
+    let waits: Vec<Duration> = (0..4).map(|_| limiter.reserve_at(now)).collect();
+    assert_eq!(
+        waits,
+        vec![
+            Duration::ZERO,
+            Duration::from_millis(100),
+            Duration::from_millis(200),
+            Duration::from_millis(300),
+        ]
+    );
+
+    // Once idle past the booked slots, calls go through immediately again.
+    assert_eq!(
+        limiter.reserve_at(now + Duration::from_secs(1)),
+        Duration::ZERO
+    );
+}
+
+#[test]
+fn test_rate_limiter_allows_bursts() {
+    let burst = NonZeroU32::new(3).unwrap();
+    let limiter = RateLimiter::new(MaxRps::new(10.0).unwrap(), burst).unwrap();
+    let now = Instant::now();
+    let waits: Vec<Duration> = (0..5).map(|_| limiter.reserve_at(now)).collect();
+    assert_eq!(
+        waits,
+        vec![
+            Duration::ZERO,
+            Duration::ZERO,
+            Duration::ZERO,
+            Duration::from_millis(100),
+            Duration::from_millis(200),
+        ]
+    );
+
+    // After idling long enough the full burst is available again.
+    let later = now + Duration::from_secs(2);
+    assert_eq!(limiter.reserve_at(later), Duration::ZERO);
+    assert_eq!(limiter.reserve_at(later), Duration::ZERO);
+    assert_eq!(limiter.reserve_at(later), Duration::ZERO);
+}
@@ -0,0 +1,224 @@
+use std::collections::hash_map::RandomState;
+use std::hash::BuildHasher;
+use std::time::Duration;
+
+use serde::Deserialize;
+
+/// Upper bound on a single backoff delay.
+const MAX_BACKOFF: Duration = Duration::from_secs(30);
+
+/// Retry policy for embedding requests, loaded from `[embedding.retry]`.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RetryConfig {
+    /// Retries after the first attempt; 0 disables retrying.
+    pub max_retries: u32,
+    /// Delay before the first retry, doubled on each further retry.
+    pub initial_backoff_ms: u64,
+}
+
+impl Default for RetryConfig {
+    fn default() -> Self {
+        Self {
+            max_retries: 3,
+            initial_backoff_ms: 500,
+        }
+    }
+}
+
+/// Errors that can tell whether retrying the failed call may succeed.
+pub trait Classify {
+    fn failure_kind(&self) -> FailureKind;
+}
+
+/// Why an embedding request failed, as far as retrying is concerned.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FailureKind {
+    Timeout,
+    ConnectionReset,
+    /// The server answered with this HTTP status.
+    Status(u16),
+    /// The input was empty, so the request was never sent.
+    EmptyContent,
+}
+
+impl FailureKind {
+    /// Timeouts, dropped connections, 5xx, 408 and 429 are transient;
+    /// other client errors and empty input will fail again.
+    pub fn is_retryable(self) -> bool {
+        match self {
+            FailureKind::Timeout | FailureKind::ConnectionReset => true,
+            FailureKind::Status(status) => status >= 500 || status == 408 || status == 429,
+            FailureKind::EmptyContent => false,
+        }
+    }
+}
+
+impl RetryConfig {
+    /// Delay before retry number `retry` (0-based), or `None` when the
+    /// retries are exhausted or the failure is not retryable.
+    ///
+    /// `jitter` in `[0, 1]` scales the exponential delay between 50% and
+    /// 100%, so concurrent callers do not retry in lockstep.
+    pub fn backoff(&self, retry: u32, failure: FailureKind, jitter: f64) -> Option<Duration> {
+        if retry >= self.max_retries || !failure.is_retryable() {
+            return None;
+        }
+        let base = 2u32
+            .checked_pow(retry)
+            .and_then(|factor| Duration::from_millis(self.initial_backoff_ms).checked_mul(factor))
+            .unwrap_or(MAX_BACKOFF)
+            .min(MAX_BACKOFF);
+        Some(base.mul_f64(0.5 + jitter.clamp(0.0, 1.0) / 2.0))
+    }
+}
+
+impl Classify for FailureKind {
+    fn failure_kind(&self) -> FailureKind {
+        *self
+    }
+}
+
+/// Calls `op` until it succeeds, its failure is not retryable or the
+/// retries are exhausted, sleeping with jittered backoff in between.
+///
+/// `op` receives the 0-based attempt number. The last error is returned
+/// when every attempt failed.
+pub fn retry<T, E, F>(config: &RetryConfig, op: F) -> Result<T, E>
+where
+    E: Classify,
+    F: FnMut(u32) -> Result<T, E>,
+{
+    retry_with_sleep(config, op, std::thread::sleep)
+}
+
+/// [`retry`] with the sleep between attempts supplied by the caller.
+pub fn retry_with_sleep<T, E, F, S>(config: &RetryConfig, mut op: F, mut sleep: S) -> Result<T, E>
+where
+    E: Classify,
+    F: FnMut(u32) -> Result<T, E>,
+    S: FnMut(Duration),
+{
+    let mut attempt = 0;
+    loop {
+        let error = match op(attempt) {
+            Ok(value) => return Ok(value),
+            Err(error) => error,
+        };
+        match config.backoff(attempt, error.failure_kind(), jitter()) {
+            Some(delay) => sleep(delay),
+            None => return Err(error),
+        }
+        attempt += 1;
+    }
+}
+
+/// A value in `[0, 1)` that differs between calls and processes.
+fn jitter() -> f64 {
+    (RandomState::new().hash_one(0u8) >> 11) as f64 / (1u64 << 53) as f64
+}
+
+#[test]
+fn test_failure_kind_is_retryable() {
+    assert!(FailureKind::Timeout.is_retryable());
+    assert!(FailureKind::ConnectionReset.is_retryable());
+    assert!(FailureKind::Status(503).is_retryable());
+    assert!(FailureKind::Status(429).is_retryable());
+    assert!(!FailureKind::Status(400).is_retryable());
+    assert!(!FailureKind::Status(404).is_retryable());
+    assert!(!FailureKind::EmptyContent.is_retryable());
+}
+
+#[test]
+fn test_backoff_grows_exponentially_until_exhausted() {
+    let config = RetryConfig::default();
+    let delays: Vec<Option<Duration>> = (0..4)
+        .map(|retry| config.backoff(retry, FailureKind::Status(503), 1.0))
+        .collect();
+    assert_eq!(
+        delays,
+        vec![
+            Some(Duration::from_millis(500)),
+            Some(Duration::from_millis(1000)),
+            Some(Duration::from_millis(2000)),
+            None,
+        ]
+    );
+}
+
+#[test]
+fn test_backoff_jitter_and_cap() {
+    let config = RetryConfig {
+        max_retries: 40,
+        initial_backoff_ms: 1000,
+    };
+    assert_eq!(
+        config.backoff(0, FailureKind::Timeout, 0.0),
+        Some(Duration::from_millis(500))
+    );
+    assert_eq!(
+        config.backoff(35, FailureKind::Timeout, 1.0),
+        Some(MAX_BACKOFF)
+    );
+    assert_eq!(config.backoff(0, FailureKind::Status(400), 1.0), None);
+}
+
+#[test]
+fn test_retry_until_success() {
+    let config = RetryConfig::default();
+    let mut delays = Vec::new();
+    let result = retry_with_sleep(
+        &config,
+        |attempt| {
+            if attempt < 2 {
+                Err(FailureKind::Status(503))
+            } else {
+                Ok(attempt)
+            }
+        },
+        |delay| delays.push(delay),
+    );
+    assert_eq!(result, Ok(2));
+    assert_eq!(delays.len(), 2);
+    assert!((Duration::from_millis(250)..=Duration::from_millis(500)).contains(&delays[0]));
+    assert!((Duration::from_millis(500)..=Duration::from_millis(1000)).contains(&delays[1]));
+}
+
+#[test]
+fn test_retry_gives_up() {
+    let config = RetryConfig {
+        max_retries: 2,
+        initial_backoff_ms: 10,
+    };
+    let mut attempts = 0;
+    let mut sleeps = 0;
+    let result: Result<(), _> = retry_with_sleep(
+        &config,
+        |_| {
+            attempts += 1;
+            Err(FailureKind::Timeout)
+        },
+        |_| sleeps += 1,
+    );
+    assert_eq!(result, Err(FailureKind::Timeout));
+    assert_eq!((attempts, sleeps), (3, 2));
+
+    let mut attempts = 0;
+    let result: Result<(), _> = retry_with_sleep(
+        &config,
+        |_| {
+            attempts += 1;
+            Err(FailureKind::Status(400))
+        },
+        |_| panic!("a client error was retried"),
+    );
+    assert_eq!(result, Err(FailureKind::Status(400)));
+    assert_eq!(attempts, 1);
+}
+
+#[test]
+fn test_jitter_in_unit_range() {
+    for _ in 0..100 {
+        assert!((0.0..1.0).contains(&jitter()));
+    }
+}
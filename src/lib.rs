@@ -1,3 +1,13 @@
+pub mod archive;
+pub mod checkpoint;
+pub mod config;
+pub mod embeddings;
+pub mod markup;
+pub mod organizer;
+pub mod search;
+pub mod tagging;
+pub mod utils;
+
 pub fn default_fn() -> i32 {
     42
 }
@@ -0,0 +1,564 @@
+use std::path::Path;
+
+use serde::Serialize;
+
+use crate::tagging::{generate_tags, TagOptions};
+use crate::utils::extension_of;
+
+/// A document heading, with its level from 1 (`#`, `<h1>`) to 6.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Heading {
+    pub level: u8,
+    pub text: String,
+}
+
+/// An outbound link and its visible text.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Link {
+    pub text: String,
+    pub target: String,
+}
+
+/// Structure extracted from a Markdown or HTML document.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct Markup {
+    pub headings: Vec<Heading>,
+    pub links: Vec<Link>,
+}
+
+impl Markup {
+    /// Extracts the structure of `text` when `path` is a Markdown or HTML
+    /// file.
+    pub fn for_path(path: &Path, text: &str) -> Option<Self> {
+        match extension_of(path)?.as_str() {
+            "md" | "markdown" => Some(Self::from_markdown(text)),
+            "html" | "htm" => Some(Self::from_html(text)),
+            _ => None,
+        }
+    }
+
+    /// Extracts ATX and setext headings and inline, reference and
+    /// autolinks, ignoring anything inside code blocks and code spans.
+    pub fn from_markdown(text: &str) -> Self {
+        let mut markup = Self::default();
+        let mut fence: Option<(char, usize)> = None;
+        let mut paragraph: Option<&str> = None;
+
+        for line in text.lines() {
+            if let Some((marker, len)) = fence {
+                if fence_marker(line).is_some_and(|(m, l)| m == marker && l >= len) {
+                    fence = None;
+                }
+                continue;
+            }
+            if let Some(opening) = fence_marker(line) {
+                fence = Some(opening);
+                paragraph = None;
+                continue;
+            }
+
+            if let Some((level, heading)) = atx_heading(line) {
+                markup.push_heading(level, &strip_inline_markdown(heading));
+                markup.scan_markdown_links(heading);
+                paragraph = None;
+                continue;
+            }
+            if let Some(level) = paragraph.and_then(|_| setext_underline(line)) {
+                if let Some(previous) = paragraph.take() {
+                    markup.push_heading(level, &strip_inline_markdown(previous));
+                }
+                continue;
+            }
+
+            let trimmed = line.trim();
+            if trimmed.is_empty() || line.starts_with("    ") || line.starts_with('\t') {
+                paragraph = None;
+                continue;
+            }
+            if let Some(link) = reference_definition(trimmed) {
+                markup.links.push(link);
+                continue;
+            }
+            markup.scan_markdown_links(trimmed);
+            paragraph = (!is_list_item(trimmed) && !trimmed.starts_with('>')).then_some(trimmed);
+        }
+        markup
+    }
+
+    /// Extracts `<h1>`–`<h6>` headings and `<a href>` links, skipping
+    /// comments, scripts and styles.
+    pub fn from_html(html: &str) -> Self {
+        let mut markup = Self::default();
+        let mut heading: Option<(u8, String)> = None;
+        let mut link: Option<(String, String)> = None;
+        let mut rest = html;
+
+        while let Some(start) = rest.find('<') {
+            let text = decode_entities(&rest[..start]);
+            if let Some((_, buf)) = heading.as_mut() {
+                buf.push_str(&text);
+            }
+            if let Some((_, buf)) = link.as_mut() {
+                buf.push_str(&text);
+            }
+            rest = &rest[start..];
+
+            if let Some(comment) = rest.strip_prefix("<!--") {
+                rest = comment.find("-->").map_or("", |end| &comment[end + 3..]);
+                continue;
+            }
+            let Some(end) = rest.find('>') else { break };
+            let tag = &rest[1..end];
+            rest = &rest[end + 1..];
+
+            let closing = tag.starts_with('/');
+            let tag = tag.trim_start_matches('/');
+            let name_len = tag
+                .find(|c: char| c.is_whitespace() || c == '/')
+                .unwrap_or(tag.len());
+            let name = tag[..name_len].to_ascii_lowercase();
+
+            match name.as_str() {
+                "script" | "style" if !closing => {
+                    let close = format!("</{}", name);
+                    rest = find_ascii_case_insensitive(rest, &close).map_or("", |i| &rest[i..]);
+                }
+                "a" if closing => {
+                    if let Some((target, text)) = link.take() {
+                        markup.links.push(Link {
+                            text: collapse_whitespace(&text),
+                            target,
+                        });
+                    }
+                }
+                "a" => {
+                    link = html_attribute(&tag[name_len..], "href")
+                        .filter(|href| !href.is_empty())
+                        .map(|href| (href, String::new()));
+                }
+                _ => {
+                    let level = match name.as_bytes() {
+                        [b'h', level @ b'1'..=b'6'] => level - b'0',
+                        _ => continue,
+                    };
+                    if closing {
+                        if let Some((level, text)) = heading.take() {
+                            markup.push_heading(level, &text);
+                        }
+                    } else {
+                        heading = Some((level, String::new()));
+                    }
+                }
+            }
+        }
+        markup
+    }
+
+    /// Heading texts, one per line, in document order.
+    pub fn heading_text(&self) -> String {
+        self.headings
+            .iter()
+            .map(|h| h.text.as_str())
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    /// Tags a document from its headings and content through
+    /// [`generate_tags`].
+    pub fn generate_tags<K, T>(
+        &self,
+        content: &str,
+        keywords: &[(K, T)],
+        options: &TagOptions,
+    ) -> Vec<String>
+    where
+        K: AsRef<str>,
+        T: AsRef<str>,
+    {
+        let text = format!("{}\n{}", self.heading_text(), content);
+        generate_tags(&text, keywords, options)
+    }
+
+    /// Headings and links as the JSON object stored with the file.
+    pub fn to_metadata(&self) -> serde_json::Value {
+        serde_json::to_value(self).unwrap_or_default()
+    }
+
+    fn push_heading(&mut self, level: u8, text: &str) {
+        let text = collapse_whitespace(text);
+        if !text.is_empty() {
+            self.headings.push(Heading { level, text });
+        }
+    }
+
+    fn scan_markdown_links(&mut self, line: &str) {
+        let line = strip_code_spans(line);
+        let bytes = line.as_bytes();
+        let mut i = 0;
+        while i < bytes.len() {
+            match bytes[i] {
+                b'<' => {
+                    let autolink = line[i + 1..]
+                        .find('>')
+                        .map(|end| &line[i + 1..i + 1 + end])
+                        .filter(|url| url.contains("://") && !url.contains(char::is_whitespace));
+                    if let Some(url) = autolink {
+                        self.links.push(Link {
+                            text: url.to_string(),
+                            target: url.to_string(),
+                        });
+                        i += url.len() + 2;
+                        continue;
+                    }
+                }
+                b'[' => {
+                    if let Some((text, target, end)) = inline_link(&line, i) {
+                        let is_image = i > 0 && bytes[i - 1] == b'!';
+                        if !is_image {
+                            self.links.push(Link {
+                                text: collapse_whitespace(&strip_inline_markdown(text)),
+                                target: target.to_string(),
+                            });
+                        }
+                        i = end;
+                        continue;
+                    }
+                }
+                _ => {}
+            }
+            i += 1;
+        }
+    }
+}
+
+/// Opening or closing code fence: the fence character and its run length.
+pub(crate) fn fence_marker(line: &str) -> Option<(char, usize)> {
+    let trimmed = line.trim_start_matches(' ');
+    if line.len() - trimmed.len() > 3 {
+        return None;
+    }
+    let marker = trimmed.chars().next().filter(|c| *c == '`' || *c == '~')?;
+    let len = trimmed.chars().take_while(|c| *c == marker).count();
+    (len >= 3).then_some((marker, len))
+}
+
+/// ATX heading level and text, for lines such as `## Usage ##`.
+pub(crate) fn atx_heading(line: &str) -> Option<(u8, &str)> {
+    let indent = line.len() - line.trim_start_matches(' ').len();
+    if indent > 3 {
+        return None;
+    }
+    let line = &line[indent..];
+    let level = line.chars().take_while(|c| *c == '#').count();
+    if !(1..=6).contains(&level) {
+        return None;
+    }
+    let rest = &line[level..];
+    if !rest.is_empty() && !rest.starts_with([' ', '\t']) {
+        return None;
+    }
+    let text = rest.trim();
+    let text = match text.trim_end_matches('#') {
+        stripped if stripped.is_empty() || stripped.ends_with([' ', '\t']) => stripped.trim_end(),
+        _ => text,
+    };
+    Some((level as u8, text))
+}
+
+fn setext_underline(line: &str) -> Option<u8> {
+    let trimmed = line.trim();
+    if trimmed.is_empty() || line.starts_with("    ") {
+        return None;
+    }
+    if trimmed.chars().all(|c| c == '=') {
+        Some(1)
+    } else if trimmed.chars().all(|c| c == '-') {
+        Some(2)
+    } else {
+        None
+    }
+}
+
+fn is_list_item(line: &str) -> bool {
+    let unordered = line
+        .strip_prefix(['-', '*', '+'])
+        .is_some_and(|rest| rest.is_empty() || rest.starts_with(' '));
+    let digits = line.chars().take_while(|c| c.is_ascii_digit()).count();
+    let ordered = digits > 0
+        && line[digits..]
+            .strip_prefix(['.', ')'])
+            .is_some_and(|rest| rest.is_empty() || rest.starts_with(' '));
+    unordered || ordered
+}
+
+/// Reference definition such as `[docs]: https://example.com "Docs"`.
+fn reference_definition(line: &str) -> Option<Link> {
+    let label_end = line.strip_prefix('[')?.find("]:")?;
+    let label = &line[1..1 + label_end];
+    let target = line[label_end + 3..].split_whitespace().next()?;
+    let target = target.trim_start_matches('<').trim_end_matches('>');
+    (!label.is_empty() && !label.starts_with('^') && !target.is_empty()).then(|| Link {
+        text: label.to_string(),
+        target: target.to_string(),
+    })
+}
+
+/// Inline link starting at the `[` at `start`: its text, its target and
+/// the index just past it. Brackets in the text and parentheses in the
+/// target may nest.
+fn inline_link(line: &str, start: usize) -> Option<(&str, &str, usize)> {
+    let text_end = start + matching_close(&line[start..], '[', ']')?;
+    if !line[text_end + 1..].starts_with('(') {
+        return None;
+    }
+    let target_start = text_end + 1;
+    let target_end = target_start + matching_close(&line[target_start..], '(', ')')?;
+
+    let destination = line[target_start + 1..target_end].trim();
+    let target = match destination.strip_prefix('<') {
+        Some(bracketed) => bracketed.split('>').next().unwrap_or(""),
+        None => destination.split_whitespace().next().unwrap_or(""),
+    };
+    (!target.is_empty()).then_some((&line[start + 1..text_end], target, target_end + 1))
+}
+
+/// Offset of the delimiter closing the one that `text` starts with.
+fn matching_close(text: &str, open: char, close: char) -> Option<usize> {
+    let mut depth = 0usize;
+    let mut escaped = false;
+    for (i, c) in text.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' => escaped = true,
+            c if c == open => depth += 1,
+            c if c == close => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(i);
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+fn strip_code_spans(line: &str) -> String {
+    let mut out = String::with_capacity(line.len());
+    let mut parts = line.split('`');
+    let mut in_code = false;
+    if let Some(first) = parts.next() {
+        out.push_str(first);
+    }
+    for part in parts {
+        in_code = !in_code;
+        if !in_code {
+            out.push(' ');
+            out.push_str(part);
+        }
+    }
+    out
+}
+
+/// Keeps the visible text of emphasis, links and images.
+fn strip_inline_markdown(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut i = 0;
+    while i < text.len() {
+        let rest = &text[i..];
+        let bracket = rest.strip_prefix('!').unwrap_or(rest);
+        if bracket.starts_with('[') {
+            let offset = i + rest.len() - bracket.len();
+            if let Some((inner, _, end)) = inline_link(text, offset) {
+                out.push_str(&strip_inline_markdown(inner));
+                i = end;
+                continue;
+            }
+        }
+        let c = rest.chars().next().unwrap_or_default();
+        if !matches!(c, '*' | '_' | '`') {
+            out.push(c);
+        }
+        i += c.len_utf8();
+    }
+    out
+}
+
+fn html_attribute(attributes: &str, name: &str) -> Option<String> {
+    let mut rest = attributes;
+    loop {
+        rest = rest.trim_start_matches(|c: char| c.is_whitespace() || c == '/');
+        if rest.is_empty() {
+            return None;
+        }
+        let key_len = rest
+            .find(|c: char| c.is_whitespace() || c == '=' || c == '/')
+            .unwrap_or(rest.len());
+        let key = &rest[..key_len];
+        rest = rest[key_len..].trim_start();
+
+        let mut value = "";
+        if let Some(after_eq) = rest.strip_prefix('=') {
+            let after_eq = after_eq.trim_start();
+            let (parsed, remaining) = match after_eq.chars().next() {
+                Some(quote @ ('"' | '\'')) => {
+                    let body = &after_eq[1..];
+                    let end = body.find(quote).unwrap_or(body.len());
+                    (&body[..end], body.get(end + 1..).unwrap_or(""))
+                }
+                _ => {
+                    let end = after_eq.find(char::is_whitespace).unwrap_or(after_eq.len());
+                    after_eq.split_at(end)
+                }
+            };
+            value = parsed;
+            rest = remaining;
+        }
+        if key.eq_ignore_ascii_case(name) {
+            return Some(decode_entities(value).trim().to_string());
+        }
+    }
+}
+
+fn find_ascii_case_insensitive(haystack: &str, needle: &str) -> Option<usize> {
+    haystack
+        .as_bytes()
+        .windows(needle.len())
+        .position(|window| window.eq_ignore_ascii_case(needle.as_bytes()))
+}
+
+fn decode_entities(text: &str) -> String {
+    if !text.contains('&') {
+        return text.to_string();
+    }
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&nbsp;", " ")
+        .replace("&amp;", "&")
+}
+
+fn collapse_whitespace(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+#[test]
+fn test_markdown_headings_and_links() {
+    let doc = "\
+# Project *Notes* #
+
+Intro with a [guide](https://example.com/guide \"Guide\") and <https://rust-lang.org>.
+
+Setext Title
+============
+
+- item with [wiki](https://en.wikipedia.org/wiki/Rust_(language))
+  - nested [![badge](badge.svg)](https://ci.example.com)
+- `[not](a-link)` in code
+
+```markdown
+# Not a heading
+[hidden](https://hidden.example)
+```
+
+### Usage
+
+[docs]: https://docs.example.com \"Docs\"
+";
+    let markup = Markup::from_markdown(doc);
+    assert_eq!(
+        markup.headings,
+        vec![
+            Heading {
+                level: 1,
+                text: "Project Notes".to_string()
+            },
+            Heading {
+                level: 1,
+                text: "Setext Title".to_string()
+            },
+            Heading {
+                level: 3,
+                text: "Usage".to_string()
+            },
+        ]
+    );
+    let links: Vec<(&str, &str)> = markup
+        .links
+        .iter()
+        .map(|l| (l.text.as_str(), l.target.as_str()))
+        .collect();
+    assert_eq!(
+        links,
+        vec![
+            ("guide", "https://example.com/guide"),
+            ("https://rust-lang.org", "https://rust-lang.org"),
+            ("wiki", "https://en.wikipedia.org/wiki/Rust_(language)"),
+            ("badge", "https://ci.example.com"),
+            ("docs", "https://docs.example.com"),
+        ]
+    );
+}
+
+#[test]
+fn test_markdown_list_is_not_setext_heading() {
+    let markup = Markup::from_markdown("- first\n---\n#hashtag\n####### seven");
+    assert!(markup.headings.is_empty());
+}
+
+#[test]
+fn test_html_headings_and_links() {
+    let html = r#"<html><head><title>Notes</title>
+<script>var s = "<h1>fake</h1>";</script></head>
+<body>
+<!-- <a href="/commented">old</a> -->
+<H1 class="title">Tax &amp; snake_case Invoices</H1>
+<h2>See <a href='/archive'>the archive</a></h2>
+<ul><li><a class=nav href=/about>About
+  us</a></li></ul>
+<a name="anchor">no href</a>
+</body></html>"#;
+    let markup = Markup::from_html(html);
+    assert_eq!(
+        markup.headings,
+        vec![
+            Heading {
+                level: 1,
+                text: "Tax & snake_case Invoices".to_string()
+            },
+            Heading {
+                level: 2,
+                text: "See the archive".to_string()
+            },
+        ]
+    );
+    assert_eq!(
+        markup.links,
+        vec![
+            Link {
+                text: "the archive".to_string(),
+                target: "/archive".to_string()
+            },
+            Link {
+                text: "About us".to_string(),
+                target: "/about".to_string()
+            },
+        ]
+    );
+}
+
+#[test]
+fn test_markup_feeds_headings_into_tags() {
+    let markup = Markup::for_path(Path::new("notes.html"), "<h1>Invoice</h1>").unwrap();
+    let keywords = [("invoice", "financial")];
+    assert_eq!(
+        markup.generate_tags("body text", &keywords, &TagOptions::default()),
+        vec!["financial"]
+    );
+    assert_eq!(
+        markup.to_metadata()["headings"][0]["level"],
+        serde_json::json!(1)
+    );
+    assert!(Markup::for_path(Path::new("notes.txt"), "# Title").is_none());
+}
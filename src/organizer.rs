@@ -0,0 +1,125 @@
+use std::time::UNIX_EPOCH;
+
+use serde::Deserialize;
+
+use crate::utils::FileMeta;
+
+/// Folder for files without any tag under the `uncategorized` strategy.
+pub const UNCATEGORIZED_FOLDER: &str = "uncategorized";
+
+/// Where files with an empty tag list are placed.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum UntaggedStrategy {
+    /// A single `uncategorized/` folder.
+    #[default]
+    Uncategorized,
+    /// `other/<ext>/`, or `other/no-extension/`.
+    ByExtension,
+    /// `YYYY/MM/` from the creation date, else the modification date.
+    ByDate,
+}
+
+/// Folder generation settings, loaded from the `[organizer]` section.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct OrganizerConfig {
+    pub untagged_strategy: UntaggedStrategy,
+}
+
+impl OrganizerConfig {
+    /// Folder components for a file whose tag list is empty.
+    ///
+    /// Files without the extension or date a strategy needs fall back to
+    /// `uncategorized/`.
+    pub fn untagged_folders(&self, meta: &FileMeta) -> Vec<String> {
+        let folders = match self.untagged_strategy {
+            UntaggedStrategy::Uncategorized => None,
+            UntaggedStrategy::ByExtension => Some(vec![
+                "other".to_string(),
+                meta.extension
+                    .clone()
+                    .unwrap_or_else(|| "no-extension".to_string()),
+            ]),
+            UntaggedStrategy::ByDate => meta.created.or(meta.modified).and_then(|time| {
+                let secs = time.duration_since(UNIX_EPOCH).ok()?.as_secs();
+                let (year, month, _) = civil_from_days((secs / 86_400) as i64);
+                Some(vec![year.to_string(), format!("{:02}", month)])
+            }),
+        };
+        folders.unwrap_or_else(|| vec![UNCATEGORIZED_FOLDER.to_string()])
+    }
+}
+
+/// Converts days since 1970-01-01 to a `(year, month, day)` UTC date.
+pub(crate) fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
+#[cfg(test)]
+fn meta(extension: Option<&str>, created: Option<u64>, modified: Option<u64>) -> FileMeta {
+    use std::time::Duration;
+
+    FileMeta {
+        path: "download".into(),
+        extension: extension.map(str::to_string),
+        size: 0,
+        created: created.map(|secs| UNIX_EPOCH + Duration::from_secs(secs)),
+        modified: modified.map(|secs| UNIX_EPOCH + Duration::from_secs(secs)),
+        hash: None,
+        protected: false,
+    }
+}
+
+#[test]
+fn test_untagged_folders_per_strategy() {
+    // 2024-03-15T12:00:00Z and 2023-12-31T23:59:59Z.
+    let file = meta(Some("pdf"), Some(1_710_504_000), Some(1_704_067_199));
+    let folders = |untagged_strategy| OrganizerConfig { untagged_strategy }.untagged_folders(&file);
+
+    assert_eq!(
+        folders(UntaggedStrategy::Uncategorized),
+        vec!["uncategorized"]
+    );
+    assert_eq!(folders(UntaggedStrategy::ByExtension), vec!["other", "pdf"]);
+    assert_eq!(folders(UntaggedStrategy::ByDate), vec!["2024", "03"]);
+}
+
+#[test]
+fn test_untagged_folders_fallbacks() {
+    let by_extension = OrganizerConfig {
+        untagged_strategy: UntaggedStrategy::ByExtension,
+    };
+    assert_eq!(
+        by_extension.untagged_folders(&meta(None, None, None)),
+        vec!["other", "no-extension"]
+    );
+
+    let by_date = OrganizerConfig {
+        untagged_strategy: UntaggedStrategy::ByDate,
+    };
+    assert_eq!(
+        by_date.untagged_folders(&meta(None, None, Some(1_704_067_199))),
+        vec!["2023", "12"]
+    );
+    assert_eq!(
+        by_date.untagged_folders(&meta(None, None, None)),
+        vec!["uncategorized"]
+    );
+}
+
+#[test]
+fn test_civil_from_days() {
+    assert_eq!(civil_from_days(0), (1970, 1, 1));
+    assert_eq!(civil_from_days(19_782), (2024, 2, 29));
+    assert_eq!(civil_from_days(-1), (1969, 12, 31));
+}
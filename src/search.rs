@@ -0,0 +1,312 @@
+use crate::embeddings::cosine_similarity;
+use crate::embeddings::quantize::StoredEmbedding;
+use crate::tagging::normalize_tag;
+use crate::utils::{normalize_extension, FileMeta};
+
+/// Results per page when the caller does not choose a limit.
+pub const DEFAULT_PAGE_SIZE: usize = 20;
+
+/// Document fields a [`SearchFilter`] can restrict on; an index backend
+/// must declare them filterable when it is created.
+pub const FILTERABLE_ATTRIBUTES: &[&str] = &["extension", "tags"];
+
+/// An indexed file as seen by search.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SearchDocument {
+    pub meta: FileMeta,
+    pub tags: Vec<String>,
+    /// Stored embedding; files indexed without one are never returned by
+    /// [`search_semantic`].
+    pub embedding: Option<StoredEmbedding>,
+}
+
+/// Returns the `limit` documents nearest to `query_embedding`, most
+/// similar first, with their cosine similarity.
+///
+/// Documents without an embedding, or whose embedding has another
+/// dimension than the query, are skipped. Equal scores are ordered by
+/// path so results are stable.
+pub fn search_semantic(
+    documents: &[SearchDocument],
+    query_embedding: &[f32],
+    limit: usize,
+) -> Vec<(FileMeta, f32)> {
+    let mut hits: Vec<(&FileMeta, f32)> = documents
+        .iter()
+        .filter_map(|document| {
+            let embedding = document.embedding.as_ref()?.to_f32();
+            let score = cosine_similarity(query_embedding, &embedding)?;
+            Some((&document.meta, score))
+        })
+        .collect();
+    hits.sort_by(|(a, x), (b, y)| y.total_cmp(x).then_with(|| a.path.cmp(&b.path)));
+    hits.into_iter()
+        .take(limit)
+        .map(|(meta, score)| (meta.clone(), score))
+        .collect()
+}
+
+/// Restricts search results by extension and tag.
+///
+/// A document passes when its extension is one of `extensions` and it has
+/// at least one of `tags`; an empty list does not restrict that field.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SearchFilter {
+    extensions: Vec<String>,
+    tags: Vec<String>,
+}
+
+impl SearchFilter {
+    pub fn new<S: AsRef<str>>(extensions: &[S], tags: &[S]) -> Self {
+        Self {
+            extensions: extensions
+                .iter()
+                .map(|e| normalize_extension(e.as_ref()))
+                .collect(),
+            tags: tags.iter().map(|t| normalize_tag(t.as_ref())).collect(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.extensions.is_empty() && self.tags.is_empty()
+    }
+
+    pub fn matches(&self, document: &SearchDocument) -> bool {
+        let extension_ok = self.extensions.is_empty()
+            || document
+                .meta
+                .extension
+                .as_ref()
+                .is_some_and(|e| self.extensions.contains(e));
+        let tags_ok = self.tags.is_empty()
+            || document
+                .tags
+                .iter()
+                .any(|t| self.tags.contains(&normalize_tag(t)));
+        extension_ok && tags_ok
+    }
+
+    /// The filter as a Meilisearch filter expression, such as
+    /// `extension IN ["pdf"] AND tags IN ["financial"]`, or `None` when
+    /// it does not restrict anything.
+    pub fn to_expression(&self) -> Option<String> {
+        let clauses: Vec<String> = [("extension", &self.extensions), ("tags", &self.tags)]
+            .into_iter()
+            .filter(|(_, values)| !values.is_empty())
+            .map(|(field, values)| {
+                let quoted: Vec<String> = values.iter().map(|v| quote(v)).collect();
+                format!("{} IN [{}]", field, quoted.join(", "))
+            })
+            .collect();
+        (!clauses.is_empty()).then(|| clauses.join(" AND "))
+    }
+}
+
+fn quote(value: &str) -> String {
+    format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+/// Returns the documents whose path contains `query`, ignoring case, and
+/// that pass `filter`, in path order. An empty query matches every path.
+pub fn search_filtered(
+    documents: &[SearchDocument],
+    query: &str,
+    filter: &SearchFilter,
+) -> Vec<FileMeta> {
+    let query = query.to_lowercase();
+    let mut hits: Vec<FileMeta> = documents
+        .iter()
+        .filter(|document| {
+            document
+                .meta
+                .path
+                .to_string_lossy()
+                .to_lowercase()
+                .contains(&query)
+                && filter.matches(document)
+        })
+        .map(|document| document.meta.clone())
+        .collect();
+    hits.sort_by(|a, b| a.path.cmp(&b.path));
+    hits
+}
+
+/// Cuts the page of `limit` hits starting at `offset` out of `hits`,
+/// returning it with the total number of hits.
+pub fn paginate<T>(hits: Vec<T>, offset: usize, limit: usize) -> (Vec<T>, usize) {
+    let total = hits.len();
+    (hits.into_iter().skip(offset).take(limit).collect(), total)
+}
+
+/// Like [`search_filtered`] without a filter, returning one page of hits
+/// and the total number of hits.
+pub fn search_paginated(
+    documents: &[SearchDocument],
+    query: &str,
+    offset: usize,
+    limit: usize,
+) -> (Vec<FileMeta>, usize) {
+    paginate(
+        search_filtered(documents, query, &SearchFilter::default()),
+        offset,
+        limit,
+    )
+}
+
+/// The first [`DEFAULT_PAGE_SIZE`] documents whose path contains `query`.
+pub fn search(documents: &[SearchDocument], query: &str) -> Vec<FileMeta> {
+    search_paginated(documents, query, 0, DEFAULT_PAGE_SIZE).0
+}
+
+/// Describes a page of results, such as `showing 21–40 of 57`, with
+/// 1-based positions.
+pub fn page_summary(offset: usize, shown: usize, total: usize) -> String {
+    if shown == 0 {
+        return format!("showing 0 of {}", total);
+    }
+    format!("showing {}–{} of {}", offset + 1, offset + shown, total)
+}
+
+#[cfg(test)]
+fn document(path: &str, embedding: Option<Vec<f32>>) -> SearchDocument {
+    SearchDocument {
+        meta: FileMeta {
+            path: path.into(),
+            extension: crate::utils::extension_of(std::path::Path::new(path)),
+            size: 0,
+            created: None,
+            modified: None,
+            hash: None,
+            protected: false,
+        },
+        tags: Vec::new(),
+        embedding: embedding.map(StoredEmbedding::Float),
+    }
+}
+
+#[test]
+fn test_search_semantic_ranks_by_similarity() {
+    let documents = vec![
+        document("far.txt", Some(vec![0.0, 1.0])),
+        document("near.txt", Some(vec![1.0, 0.1])),
+        document("exact.txt", Some(vec![2.0, 0.0])),
+    ];
+    let hits = search_semantic(&documents, &[1.0, 0.0], 2);
+    let paths: Vec<_> = hits
+        .iter()
+        .map(|(meta, _)| meta.path.to_str().unwrap())
+        .collect();
+    assert_eq!(paths, vec!["exact.txt", "near.txt"]);
+    assert!((hits[0].1 - 1.0).abs() < 1e-6);
+    assert!(hits[1].1 < 1.0 && hits[1].1 > 0.9);
+}
+
+#[test]
+fn test_search_semantic_skips_documents_without_embedding() {
+    let quantized = crate::embeddings::quantize::quantize(&[0.5, 0.5]).unwrap();
+    let documents = vec![
+        document("none.pdf", None),
+        document("other-dims.txt", Some(vec![1.0, 0.0, 0.0])),
+        document("zero.txt", Some(vec![0.0, 0.0])),
+        SearchDocument {
+            embedding: Some(StoredEmbedding::Quantized(quantized)),
+            ..document("quantized.txt", None)
+        },
+    ];
+    let hits = search_semantic(&documents, &[1.0, 1.0], 10);
+    assert_eq!(hits.len(), 1);
+    assert_eq!(hits[0].0.path, std::path::Path::new("quantized.txt"));
+    assert!(search_semantic(&documents, &[1.0, 1.0], 0).is_empty());
+    assert!(search_semantic(&[], &[1.0], 5).is_empty());
+}
+
+#[cfg(test)]
+fn tagged(path: &str, tags: &[&str]) -> SearchDocument {
+    SearchDocument {
+        tags: tags.iter().map(|t| t.to_string()).collect(),
+        ..document(path, None)
+    }
+}
+
+#[test]
+fn test_search_filtered_by_extension_and_tag() {
+    let documents = vec![
+        tagged("finance/2024/invoice.pdf", &["financial", "invoice"]),
+        tagged("finance/2024/budget.xlsx", &["financial"]),
+        tagged("medical/2024/report.pdf", &["medical"]),
+        tagged("notes/2024.txt", &[]),
+    ];
+    let paths = |hits: Vec<FileMeta>| -> Vec<String> {
+        hits.iter()
+            .map(|meta| meta.path.to_string_lossy().into_owned())
+            .collect()
+    };
+
+    let pdfs_tagged_financial = SearchFilter::new(&[".PDF"], &["Financial"]);
+    assert_eq!(
+        paths(search_filtered(&documents, "2024", &pdfs_tagged_financial)),
+        vec!["finance/2024/invoice.pdf"]
+    );
+
+    let any_financial_or_medical = SearchFilter::new(&[], &["financial", "medical"]);
+    assert_eq!(
+        paths(search_filtered(&documents, "", &any_financial_or_medical)),
+        vec![
+            "finance/2024/budget.xlsx",
+            "finance/2024/invoice.pdf",
+            "medical/2024/report.pdf",
+        ]
+    );
+
+    assert_eq!(
+        paths(search_filtered(
+            &documents,
+            "NOTES",
+            &SearchFilter::default()
+        )),
+        vec!["notes/2024.txt"]
+    );
+}
+
+#[test]
+fn test_search_filter_expression() {
+    assert_eq!(SearchFilter::default().to_expression(), None);
+    assert!(SearchFilter::new::<&str>(&[], &[]).is_empty());
+    assert_eq!(
+        SearchFilter::new(&["pdf", "docx"], &[]).to_expression(),
+        Some(r#"extension IN ["pdf", "docx"]"#.to_string())
+    );
+    assert_eq!(
+        SearchFilter::new(&["pdf"], &["financial"]).to_expression(),
+        Some(r#"extension IN ["pdf"] AND tags IN ["financial"]"#.to_string())
+    );
+    assert_eq!(
+        SearchFilter::new(&[], &[r#"say "hi"\"#]).to_expression(),
+        Some(r#"tags IN ["say-\"hi\"\\"]"#.to_string())
+    );
+}
+
+#[test]
+fn test_search_paginated_pages_and_total() {
+    let documents: Vec<SearchDocument> = (0..57)
+        .map(|i| document(&format!("docs/{:02}.txt", i), None))
+        .collect();
+
+    let (page, total) = search_paginated(&documents, "docs", 20, 20);
+    assert_eq!(total, 57);
+    assert_eq!(page.len(), 20);
+    assert_eq!(page[0].path, std::path::Path::new("docs/20.txt"));
+    assert_eq!(page_summary(20, page.len(), total), "showing 21–40 of 57");
+
+    let (last, total) = search_paginated(&documents, "docs", 40, 20);
+    assert_eq!(last.len(), 17);
+    assert_eq!(page_summary(40, last.len(), total), "showing 41–57 of 57");
+
+    let (past_end, total) = search_paginated(&documents, "docs", 100, 20);
+    assert!(past_end.is_empty());
+    assert_eq!(page_summary(100, past_end.len(), total), "showing 0 of 57");
+
+    let first = search(&documents, "docs");
+    assert_eq!(first.len(), DEFAULT_PAGE_SIZE);
+    assert_eq!(first, search_paginated(&documents, "docs", 0, 20).0);
+}
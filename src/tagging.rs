@@ -0,0 +1,883 @@
+use std::io;
+use std::path::PathBuf;
+
+use serde::Deserialize;
+
+/// Default placeholder emitted when no other tag survives normalization.
+pub const UNKNOWN_TAG: &str = "unknown";
+
+/// Words ending in `s` that must not lose it when singularizing.
+const INVARIANT_WORDS: &[&str] = &[
+    // Singular or uncountable nouns.
+    "news",
+    "series",
+    "species",
+    "physics",
+    "mathematics",
+    "economics",
+    "statistics",
+    "politics",
+    "diabetes",
+    "rabies",
+    "measles",
+    "pandas",
+    "gas",
+    "lens",
+    "canvas",
+    "atlas",
+    "alias",
+    "bias",
+    // Greek singulars in -os.
+    "chaos",
+    "kudos",
+    "ethos",
+    "pathos",
+    "cosmos",
+    "asbestos",
+    // Product names.
+    "kubernetes",
+    "windows",
+    "postgres",
+    "jenkins",
+    "macos",
+    "ios",
+];
+
+/// Plurals in `-us` whose singular drops the `s`; every other `-us` word is
+/// taken as singular, like `status`, `bonus` or `prospectus`.
+const US_PLURALS: &[&str] = &["menus", "gurus", "emus"];
+
+/// Singulars in `-us` whose plural adds `-es`, as in `buses`.
+const US_SINGULARS: &[&str] = &[
+    "bus",
+    "virus",
+    "status",
+    "campus",
+    "corpus",
+    "bonus",
+    "census",
+    "focus",
+    "nexus",
+    "radius",
+    "syllabus",
+    "cactus",
+    "octopus",
+    "stimulus",
+    "consensus",
+    "prospectus",
+];
+
+/// Plurals in `-ies` whose singular ends in `-ie`, not `-y`.
+const IE_PLURALS: &[&str] = &[
+    "movies",
+    "cookies",
+    "zombies",
+    "rookies",
+    "calories",
+    "brownies",
+    "selfies",
+    "smoothies",
+    "hoodies",
+    "genies",
+];
+
+/// Singulars in `-che` whose plural must not lose its `e`.
+const CHE_SINGULARS: &[&str] = &["cache", "niche", "headache", "avalanche", "cliche"];
+
+/// Keyword to tag mappings used when no dictionary file replaces them.
+pub const BUILTIN_KEYWORDS: &[(&str, &str)] = &[
+    ("invoice", "financial"),
+    ("receipt", "financial"),
+    ("payment", "financial"),
+    ("bank", "financial"),
+    ("tax", "tax"),
+    ("contract", "legal"),
+    ("agreement", "legal"),
+    ("resume", "cv"),
+    ("curriculum", "cv"),
+    ("meeting", "meeting"),
+    ("recipe", "cooking"),
+    ("fn", "programming"),
+    ("def", "programming"),
+    ("import", "programming"),
+];
+
+/// Tags that name a top-level folder.
+pub const TOP_LEVEL_CATEGORIES: &[&str] = &[
+    "documents",
+    "financial",
+    "legal",
+    "programming",
+    "media",
+    "personal",
+    "work",
+    "education",
+];
+
+/// Tags that name a folder under a top-level category.
+pub const MID_LEVEL_CATEGORIES: &[&str] = &[
+    "invoice", "tax", "contract", "cv", "meeting", "report", "photos", "music", "cooking",
+];
+
+/// Tags precise enough for the deepest folder level.
+pub const SPECIFIC_TAGS: &[&str] = &[
+    "rust",
+    "python",
+    "javascript",
+    "typescript",
+    "go",
+    "java",
+    "receipt",
+    "payslip",
+    "nda",
+];
+
+/// How a dictionary file combines with [`BUILTIN_KEYWORDS`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DictionaryMode {
+    /// Add the file's mappings, overriding built-in keywords it redefines.
+    #[default]
+    Merge,
+    /// Use the file's mappings only.
+    Replace,
+}
+
+/// Options applied to every tag list, whatever source produced it.
+///
+/// Loaded from the `[tagging]` section of the config.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TagOptions {
+    /// Tags that must never be emitted, compared after normalization.
+    pub blocklist: Vec<String>,
+    /// Collapse plural tags onto their singular form (off by default).
+    pub singularize: bool,
+    /// Placeholder used when a file has no other tag.
+    pub unknown_tag: String,
+    /// Drop the placeholder as soon as any real tag is present.
+    pub drop_unknown_when_tagged: bool,
+    /// TOML file of `keyword = "tag"` lines for dictionary tagging.
+    pub dictionary_file: Option<PathBuf>,
+    /// How `dictionary_file` combines with the built-in keywords.
+    pub dictionary_mode: DictionaryMode,
+    /// Limits for dictionary keyword scanning.
+    pub scan: KeywordScan,
+    /// Category lists used to build folder hierarchies from tags.
+    pub categories: CategoryLevels,
+}
+
+impl Default for TagOptions {
+    fn default() -> Self {
+        Self {
+            blocklist: Vec::new(),
+            singularize: false,
+            unknown_tag: UNKNOWN_TAG.to_string(),
+            drop_unknown_when_tagged: true,
+            dictionary_file: None,
+            dictionary_mode: DictionaryMode::default(),
+            scan: KeywordScan::default(),
+            categories: CategoryLevels::default(),
+        }
+    }
+}
+
+impl TagOptions {
+    /// Normalizes `tag`, singularizing it when enabled.
+    pub fn canonical(&self, tag: &str) -> String {
+        let tag = normalize_tag(tag);
+        if self.singularize {
+            singularize(&tag)
+        } else {
+            tag
+        }
+    }
+
+    /// Keyword to tag mappings for dictionary tagging.
+    ///
+    /// Without a `dictionary_file` these are [`BUILTIN_KEYWORDS`]. A file
+    /// that cannot be read or parsed is an error rather than a silent
+    /// fallback to the built-in mappings.
+    pub fn keywords(&self) -> io::Result<Vec<(String, String)>> {
+        let mut keywords: Vec<(String, String)> = match self.dictionary_mode {
+            DictionaryMode::Replace if self.dictionary_file.is_some() => Vec::new(),
+            _ => BUILTIN_KEYWORDS
+                .iter()
+                .map(|(keyword, tag)| (keyword.to_string(), tag.to_string()))
+                .collect(),
+        };
+        let Some(path) = &self.dictionary_file else {
+            return Ok(keywords);
+        };
+
+        let content = std::fs::read_to_string(path)?;
+        let dictionary: toml::Table = content.parse().map_err(|e| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("invalid dictionary {}: {}", path.display(), e),
+            )
+        })?;
+        for (keyword, tag) in dictionary {
+            let Some(tag) = tag.as_str() else {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!(
+                        "invalid dictionary {}: tag for `{}` must be a string",
+                        path.display(),
+                        keyword
+                    ),
+                ));
+            };
+            match keywords
+                .iter_mut()
+                .find(|(k, _)| k.eq_ignore_ascii_case(&keyword))
+            {
+                Some((_, existing)) => *existing = tag.to_string(),
+                None => keywords.push((keyword, tag.to_string())),
+            }
+        }
+        Ok(keywords)
+    }
+}
+
+/// Lowercases and trims a tag, collapsing inner whitespace to `-`.
+pub fn normalize_tag(tag: &str) -> String {
+    tag.split_whitespace()
+        .collect::<Vec<_>>()
+        .join("-")
+        .to_lowercase()
+}
+
+/// Returns the lowercase singular form of an English plural.
+///
+/// Rules-based on purpose: only common plural suffixes are handled. Short
+/// words, words in `-ss`, `-is`, `-us` and `-js`, and known singulars such
+/// as `news` or `kubernetes` are kept as they are.
+pub fn singularize(word: &str) -> String {
+    let word = word.to_lowercase();
+    if word.len() <= 3
+        || INVARIANT_WORDS.contains(&word.as_str())
+        || word.ends_with("ss")
+        || word.ends_with("is")
+        || word.ends_with("js")
+        || (word.ends_with("us") && !US_PLURALS.contains(&word.as_str()))
+    {
+        return word;
+    }
+
+    if IE_PLURALS.contains(&word.as_str()) {
+        return word[..word.len() - 1].to_string();
+    }
+    if let Some(stem) = word.strip_suffix("yses") {
+        return format!("{}ysis", stem);
+    }
+    if let Some(stem) = word.strip_suffix("ies").filter(|stem| !stem.is_empty()) {
+        return format!("{}y", stem);
+    }
+    if let Some(stem) = word
+        .strip_suffix("es")
+        .filter(|stem| US_SINGULARS.contains(stem) || INVARIANT_WORDS.contains(stem))
+    {
+        return stem.to_string();
+    }
+    let che_singular = CHE_SINGULARS
+        .iter()
+        .any(|w| word.strip_suffix('s') == Some(w));
+    if !che_singular
+        && ["ches", "shes", "xes", "sses"]
+            .iter()
+            .any(|s| word.ends_with(s))
+    {
+        return word[..word.len() - 2].to_string();
+    }
+    if let Some(stem) = word.strip_suffix('s') {
+        return stem.to_string();
+    }
+    word
+}
+
+/// Normalizes, dedupes and filters `tags`, never returning an empty list.
+///
+/// The unknown placeholder is only kept when it is the sole tag, unless
+/// `drop_unknown_when_tagged` is disabled.
+pub fn finalize_tags<I, S>(tags: I, options: &TagOptions) -> Vec<String>
+where
+    I: IntoIterator<Item = S>,
+    S: AsRef<str>,
+{
+    let blocklist: Vec<String> = options
+        .blocklist
+        .iter()
+        .map(|t| options.canonical(t))
+        .collect();
+
+    let mut out: Vec<String> = Vec::new();
+    for tag in tags {
+        let tag = options.canonical(tag.as_ref());
+        if tag.is_empty() || blocklist.contains(&tag) || out.contains(&tag) {
+            continue;
+        }
+        out.push(tag);
+    }
+
+    let unknown = options.canonical(&options.unknown_tag);
+    if options.drop_unknown_when_tagged && out.len() > 1 {
+        out.retain(|t| *t != unknown);
+    }
+    if out.is_empty() {
+        out.push(unknown);
+    }
+    out
+}
+
+/// Level of a tag in the folder hierarchy.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum CategoryLevel {
+    Top,
+    Mid,
+    Specific,
+}
+
+/// Category lists added to, or replacing, [`TOP_LEVEL_CATEGORIES`],
+/// [`MID_LEVEL_CATEGORIES`] and [`SPECIFIC_TAGS`].
+///
+/// Loaded from the `[tagging.categories]` section of the config.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CategoryLevels {
+    pub top_level: Vec<String>,
+    pub mid_level: Vec<String>,
+    pub specific: Vec<String>,
+    /// Use the configured lists only, ignoring the built-in ones.
+    pub replace: bool,
+}
+
+impl CategoryLevels {
+    /// Level of `tag`, comparing normalized tags. A tag listed at several
+    /// levels takes the highest one, so configuring `medical` as top-level
+    /// wins over a built-in lower level.
+    pub fn level(&self, tag: &str) -> Option<CategoryLevel> {
+        let tag = normalize_tag(tag);
+        let listed = |configured: &[String], builtin: &[&str]| {
+            configured.iter().any(|t| normalize_tag(t) == tag)
+                || (!self.replace && builtin.contains(&tag.as_str()))
+        };
+        if listed(&self.top_level, TOP_LEVEL_CATEGORIES) {
+            Some(CategoryLevel::Top)
+        } else if listed(&self.mid_level, MID_LEVEL_CATEGORIES) {
+            Some(CategoryLevel::Mid)
+        } else if listed(&self.specific, SPECIFIC_TAGS) {
+            Some(CategoryLevel::Specific)
+        } else {
+            None
+        }
+    }
+
+    /// Folder components for `tags`, from the top-level category down.
+    ///
+    /// Each level contributes its first matching tag. When no tag belongs
+    /// to any level, the first tag is used alone.
+    pub fn categorize<S: AsRef<str>>(&self, tags: &[S]) -> Vec<String> {
+        let mut levels: Vec<(CategoryLevel, String)> = Vec::new();
+        for tag in tags {
+            let Some(level) = self.level(tag.as_ref()) else {
+                continue;
+            };
+            if !levels.iter().any(|(l, _)| *l == level) {
+                levels.push((level, normalize_tag(tag.as_ref())));
+            }
+        }
+        levels.sort_by_key(|(level, _)| *level);
+
+        let components: Vec<String> = levels.into_iter().map(|(_, tag)| tag).collect();
+        if components.is_empty() {
+            return tags
+                .first()
+                .map(|tag| vec![normalize_tag(tag.as_ref())])
+                .unwrap_or_default();
+        }
+        components
+    }
+}
+
+/// Limits applied when scanning content for dictionary keywords.
+///
+/// Loaded from the `[tagging.scan]` section of the config.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct KeywordScan {
+    /// Only the first `window_bytes` of content are scanned.
+    pub window_bytes: usize,
+    /// A keyword must occur at least this many times to produce its tag.
+    pub min_occurrences: usize,
+}
+
+impl Default for KeywordScan {
+    fn default() -> Self {
+        Self {
+            window_bytes: 64 * 1024,
+            min_occurrences: 1,
+        }
+    }
+}
+
+/// Returns the tags whose keywords occur often enough in `content`.
+///
+/// `keywords` maps a keyword to its tag. Matches are case-insensitive and
+/// must sit on word boundaries, so `tax` does not match inside `syntax`.
+pub fn scan_keywords<K, T>(content: &str, keywords: &[(K, T)], scan: &KeywordScan) -> Vec<String>
+where
+    K: AsRef<str>,
+    T: AsRef<str>,
+{
+    let mut end = content.len().min(scan.window_bytes);
+    while !content.is_char_boundary(end) {
+        end -= 1;
+    }
+    let window = content[..end].to_lowercase();
+
+    let mut tags: Vec<String> = Vec::new();
+    for (keyword, tag) in keywords {
+        let (keyword, tag) = (keyword.as_ref(), tag.as_ref());
+        if keyword.is_empty() || tags.iter().any(|t| t == tag) {
+            continue;
+        }
+        if count_word_occurrences(&window, &keyword.to_lowercase()) >= scan.min_occurrences.max(1) {
+            tags.push(tag.to_string());
+        }
+    }
+    tags
+}
+
+/// Tags `content` from a keyword dictionary through the shared pipeline.
+///
+/// Scanning honours `options.scan`; see [`scan_keywords`]. The result goes
+/// through [`finalize_tags`].
+pub fn generate_tags<K, T>(content: &str, keywords: &[(K, T)], options: &TagOptions) -> Vec<String>
+where
+    K: AsRef<str>,
+    T: AsRef<str>,
+{
+    finalize_tags(scan_keywords(content, keywords, &options.scan), options)
+}
+
+fn count_word_occurrences(haystack: &str, word: &str) -> usize {
+    let is_word_char = |c: Option<char>| c.is_some_and(|c| c.is_alphanumeric());
+    haystack
+        .match_indices(word)
+        .filter(|(i, _)| {
+            let before = haystack[..*i].chars().next_back();
+            let after = haystack[i + word.len()..].chars().next();
+            !is_word_char(before) && !is_word_char(after)
+        })
+        .count()
+}
+
+/// Where a tag came from, used to weight it when merging.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TagSource {
+    Dictionary,
+    Llm,
+}
+
+impl TagSource {
+    /// Default confidence given to tags from this source.
+    pub fn weight(self) -> f32 {
+        match self {
+            TagSource::Dictionary => 1.0,
+            TagSource::Llm => 0.6,
+        }
+    }
+}
+
+/// A tag together with its source and confidence.
+#[derive(Debug, Clone, PartialEq)]
+pub struct WeightedTag {
+    pub tag: String,
+    pub source: TagSource,
+    pub weight: f32,
+}
+
+impl WeightedTag {
+    pub fn new(tag: impl Into<String>, source: TagSource) -> Self {
+        Self {
+            tag: tag.into(),
+            source,
+            weight: source.weight(),
+        }
+    }
+}
+
+/// Merges tags from several sources into a ranked, deduped list.
+///
+/// Tags are canonicalized before merging. A source reporting the same tag
+/// twice only counts once, with its highest weight; weights are then
+/// summed across sources, so a tag confirmed by both sources outranks
+/// either alone. Ties keep first-seen order, and the result goes through
+/// [`finalize_tags`].
+pub fn merge_weighted_tags<I>(tags: I, options: &TagOptions) -> Vec<String>
+where
+    I: IntoIterator<Item = WeightedTag>,
+{
+    let mut per_source: Vec<(String, TagSource, f32)> = Vec::new();
+    for weighted in tags {
+        let tag = options.canonical(&weighted.tag);
+        if tag.is_empty() {
+            continue;
+        }
+        match per_source
+            .iter_mut()
+            .find(|(t, source, _)| *t == tag && *source == weighted.source)
+        {
+            Some((_, _, weight)) => *weight = weight.max(weighted.weight),
+            None => per_source.push((tag, weighted.source, weighted.weight)),
+        }
+    }
+
+    let mut merged: Vec<(String, f32)> = Vec::new();
+    for (tag, _, weight) in per_source {
+        match merged.iter_mut().find(|(t, _)| *t == tag) {
+            Some((_, total)) => *total += weight,
+            None => merged.push((tag, weight)),
+        }
+    }
+
+    merged.sort_by(|a, b| b.1.total_cmp(&a.1));
+    finalize_tags(merged.into_iter().map(|(tag, _)| tag), options)
+}
+
+#[test]
+fn test_blocklist_strips_blocked_tags() {
+    let options = TagOptions {
+        blocklist: vec!["Misc".to_string(), "temp".to_string()],
+        ..TagOptions::default()
+    };
+    let tags = finalize_tags(["misc", "Financial", "TEMP", "invoice"], &options);
+    assert_eq!(tags, vec!["financial", "invoice"]);
+}
+
+#[test]
+fn test_blocklist_never_returns_empty() {
+    let options = TagOptions {
+        blocklist: vec!["misc".to_string()],
+        ..TagOptions::default()
+    };
+    assert_eq!(finalize_tags(["misc"], &options), vec![UNKNOWN_TAG]);
+}
+
+#[test]
+fn test_finalize_tags_dedupes_after_normalization() {
+    let tags = finalize_tags(
+        ["Machine Learning", "machine-learning "],
+        &TagOptions::default(),
+    );
+    assert_eq!(tags, vec!["machine-learning"]);
+}
+
+#[test]
+fn test_generate_tags_applies_blocklist() {
+    let keywords = [("invoice", "financial"), ("draft", "temp"), ("tax", "tax")];
+    let options = TagOptions {
+        blocklist: vec!["temp".to_string()],
+        ..TagOptions::default()
+    };
+    assert_eq!(
+        generate_tags("Draft invoice about syntax", &keywords, &options),
+        vec!["financial"]
+    );
+    assert_eq!(
+        generate_tags("draft", &keywords, &options),
+        vec![UNKNOWN_TAG]
+    );
+}
+
+#[test]
+fn test_merge_weighted_tags_ranks_dictionary_over_llm() {
+    let tags = merge_weighted_tags(
+        [
+            WeightedTag::new("guess", TagSource::Llm),
+            WeightedTag::new("invoice", TagSource::Dictionary),
+        ],
+        &TagOptions::default(),
+    );
+    assert_eq!(tags, vec!["invoice", "guess"]);
+}
+
+#[test]
+fn test_merge_weighted_tags_boosts_overlapping_tags() {
+    let tags = merge_weighted_tags(
+        [
+            WeightedTag::new("document", TagSource::Dictionary),
+            WeightedTag::new("financial", TagSource::Dictionary),
+            WeightedTag::new("Financial", TagSource::Llm),
+            WeightedTag::new("tax", TagSource::Llm),
+        ],
+        &TagOptions::default(),
+    );
+    assert_eq!(tags, vec!["financial", "document", "tax"]);
+}
+
+#[test]
+fn test_merge_weighted_tags_counts_each_source_once() {
+    // An LLM repeating its guess must not outrank a single dictionary match.
+    let tags = merge_weighted_tags(
+        [
+            WeightedTag::new("Financial", TagSource::Llm),
+            WeightedTag::new("financial", TagSource::Llm),
+            WeightedTag::new("invoice", TagSource::Dictionary),
+        ],
+        &TagOptions::default(),
+    );
+    assert_eq!(tags, vec!["invoice", "financial"]);
+}
+
+#[test]
+fn test_merge_weighted_tags_uses_shared_pipeline() {
+    let options = TagOptions {
+        blocklist: vec!["misc".to_string()],
+        ..TagOptions::default()
+    };
+    let tags = merge_weighted_tags(
+        [
+            WeightedTag::new("document", TagSource::Dictionary),
+            WeightedTag::new("misc", TagSource::Llm),
+        ],
+        &options,
+    );
+    assert_eq!(tags, vec!["document"]);
+
+    let tags = merge_weighted_tags([WeightedTag::new("misc", TagSource::Llm)], &options);
+    assert_eq!(tags, vec![UNKNOWN_TAG]);
+}
+
+#[test]
+fn test_singularize_common_plurals() {
+    for (plural, singular) in [
+        ("invoices", "invoice"),
+        ("reports", "report"),
+        ("categories", "category"),
+        ("boxes", "box"),
+        ("batches", "batch"),
+        ("caches", "cache"),
+        ("movies", "movie"),
+        ("analyses", "analysis"),
+        ("buses", "bus"),
+        ("viruses", "virus"),
+        ("lenses", "lens"),
+        ("houses", "house"),
+        ("causes", "cause"),
+        ("menus", "menu"),
+        ("gurus", "guru"),
+    ] {
+        assert_eq!(singularize(plural), singular, "{plural}");
+    }
+}
+
+#[test]
+fn test_singularize_keeps_singulars() {
+    for word in [
+        "news",
+        "status",
+        "plus",
+        "minus",
+        "prospectus",
+        "nodejs",
+        "pandas",
+        "analysis",
+        "address",
+        "kubernetes",
+        "bus",
+        "ios",
+    ] {
+        assert_eq!(singularize(word), word);
+    }
+}
+
+#[test]
+fn test_finalize_tags_collapses_plurals_when_enabled() {
+    let tags = ["Invoices", "invoice", "reports", "news"];
+    assert_eq!(
+        finalize_tags(tags, &TagOptions::default()),
+        vec!["invoices", "invoice", "reports", "news"]
+    );
+
+    let options = TagOptions {
+        singularize: true,
+        ..TagOptions::default()
+    };
+    assert_eq!(
+        finalize_tags(tags, &options),
+        vec!["invoice", "report", "news"]
+    );
+}
+
+#[test]
+fn test_unknown_dropped_only_when_other_tags_exist() {
+    let options = TagOptions::default();
+    assert_eq!(
+        finalize_tags(["unknown", "invoice"], &options),
+        vec!["invoice"]
+    );
+    assert_eq!(finalize_tags(["Unknown"], &options), vec![UNKNOWN_TAG]);
+
+    let keep = TagOptions {
+        drop_unknown_when_tagged: false,
+        ..TagOptions::default()
+    };
+    assert_eq!(
+        finalize_tags(["unknown", "invoice"], &keep),
+        vec!["unknown", "invoice"]
+    );
+}
+
+#[test]
+fn test_unknown_tag_is_configurable() {
+    let options = TagOptions {
+        unknown_tag: "Unsorted".to_string(),
+        ..TagOptions::default()
+    };
+    assert_eq!(
+        finalize_tags(Vec::<&str>::new(), &options),
+        vec!["unsorted"]
+    );
+    assert_eq!(
+        finalize_tags(["unsorted", "report"], &options),
+        vec!["report"]
+    );
+    // The default placeholder is an ordinary tag once another is configured.
+    assert_eq!(
+        finalize_tags(["unknown", "report"], &options),
+        vec!["unknown", "report"]
+    );
+}
+
+#[test]
+fn test_dictionary_file_merges_or_replaces() {
+    let path = std::env::temp_dir().join(format!("cognify-dictionary-{}.toml", std::process::id()));
+    std::fs::write(
+        &path,
+        "diagnosis = \"medical\"\nprescription = \"medical\"\ninvoice = \"billing\"\n",
+    )
+    .unwrap();
+    let content = "Prescription and invoice for the tax return";
+
+    let merge = TagOptions {
+        dictionary_file: Some(path.clone()),
+        ..TagOptions::default()
+    };
+    let keywords = merge.keywords().unwrap();
+    assert_eq!(
+        generate_tags(content, &keywords, &merge),
+        vec!["billing", "tax", "medical"]
+    );
+
+    let replace = TagOptions {
+        dictionary_file: Some(path.clone()),
+        dictionary_mode: DictionaryMode::Replace,
+        ..TagOptions::default()
+    };
+    let keywords = replace.keywords().unwrap();
+    assert_eq!(keywords.len(), 3);
+    assert_eq!(
+        generate_tags(content, &keywords, &replace),
+        vec!["billing", "medical"]
+    );
+
+    std::fs::write(&path, "diagnosis = 3\n").unwrap();
+    assert_eq!(
+        replace.keywords().unwrap_err().kind(),
+        io::ErrorKind::InvalidData
+    );
+    std::fs::remove_file(&path).unwrap();
+    assert!(replace.keywords().is_err());
+}
+
+#[test]
+fn test_keywords_default_to_builtin() {
+    let keywords = TagOptions::default().keywords().unwrap();
+    assert_eq!(keywords.len(), BUILTIN_KEYWORDS.len());
+
+    // Replace mode without a file keeps the built-in mappings.
+    let options = TagOptions {
+        dictionary_mode: DictionaryMode::Replace,
+        ..TagOptions::default()
+    };
+    assert_eq!(options.keywords().unwrap(), keywords);
+}
+
+#[test]
+fn test_scan_keywords_min_occurrences() {
+    let keywords = [("invoice", "financial"), ("contract", "legal")];
+    let scan = KeywordScan {
+        min_occurrences: 2,
+        ..KeywordScan::default()
+    };
+    let content = "Invoice 42. See the contract. Invoice total: 10.";
+    assert_eq!(scan_keywords(content, &keywords, &scan), vec!["financial"]);
+    assert_eq!(
+        scan_keywords(content, &keywords, &KeywordScan::default()),
+        vec!["financial", "legal"]
+    );
+    // Matches inside longer words do not count towards the threshold.
+    assert!(scan_keywords("invoices invoiced", &keywords, &scan).is_empty());
+}
+
+#[test]
+fn test_scan_keywords_window() {
+    let keywords = [("invoice", "financial"), ("caf\u{e9}", "food")];
+    let scan = KeywordScan {
+        window_bytes: 20,
+        ..KeywordScan::default()
+    };
+    let content = format!("{}invoice", " ".repeat(20));
+    assert!(scan_keywords(&content, &keywords, &scan).is_empty());
+    assert_eq!(
+        scan_keywords(&content, &keywords, &KeywordScan::default()),
+        vec!["financial"]
+    );
+    // A window ending inside a multi-byte character is cut before it.
+    let content = format!("{}caf\u{e9}", " ".repeat(16));
+    assert!(scan_keywords(&content, &keywords, &scan).is_empty());
+}
+
+#[test]
+fn test_categorize_orders_levels() {
+    let categories = CategoryLevels::default();
+    assert_eq!(
+        categories.categorize(&["rust", "Programming", "python"]),
+        vec!["programming", "rust"]
+    );
+    assert_eq!(
+        categories.categorize(&["receipt", "invoice", "financial"]),
+        vec!["financial", "invoice", "receipt"]
+    );
+    assert_eq!(
+        categories.categorize(&["holiday", "beach"]),
+        vec!["holiday"]
+    );
+    assert!(categories.categorize::<&str>(&[]).is_empty());
+}
+
+#[test]
+fn test_categorize_with_configured_top_level() {
+    let tags = ["prescription", "Medical", "report"];
+    assert_eq!(CategoryLevels::default().categorize(&tags), vec!["report"]);
+
+    let merged = CategoryLevels {
+        top_level: vec!["medical".to_string()],
+        specific: vec!["prescription".to_string()],
+        ..CategoryLevels::default()
+    };
+    assert_eq!(merged.level("medical"), Some(CategoryLevel::Top));
+    assert_eq!(
+        merged.categorize(&tags),
+        vec!["medical", "report", "prescription"]
+    );
+
+    let replaced = CategoryLevels {
+        replace: true,
+        ..merged
+    };
+    assert_eq!(replaced.level("report"), None);
+    assert_eq!(replaced.categorize(&tags), vec!["medical", "prescription"]);
+}
@@ -0,0 +1,1733 @@
+use std::collections::{HashSet, VecDeque};
+use std::fmt;
+use std::fs::{self, File};
+use std::io::{self, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use globset::{GlobBuilder, GlobSet, GlobSetBuilder};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+/// Extension allow/deny list shared by every walk loop.
+///
+/// When `include` is non-empty it acts as an allowlist and `exclude` is
+/// ignored; otherwise `exclude` acts as a denylist.
+#[derive(Debug, Clone, Default)]
+pub struct ExtensionFilter {
+    include: Vec<String>,
+    exclude: Vec<String>,
+}
+
+impl ExtensionFilter {
+    pub fn new<S: AsRef<str>>(include: &[S], exclude: &[S]) -> Self {
+        Self {
+            include: include
+                .iter()
+                .map(|e| normalize_extension(e.as_ref()))
+                .collect(),
+            exclude: exclude
+                .iter()
+                .map(|e| normalize_extension(e.as_ref()))
+                .collect(),
+        }
+    }
+
+    /// Returns whether a file at `path` should be processed.
+    pub fn matches(&self, path: &Path) -> bool {
+        let ext = extension_of(path);
+        if !self.include.is_empty() {
+            return ext.is_some_and(|e| self.include.contains(&e));
+        }
+        match ext {
+            Some(e) => !self.exclude.contains(&e),
+            None => true,
+        }
+    }
+}
+
+/// Traversal options shared by every directory walk, loaded from the
+/// `[walk]` section of the config.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct WalkOptions {
+    /// Visit files and directories whose name starts with a dot.
+    pub include_hidden: bool,
+    /// Follow symbolic links below the root instead of skipping them. The
+    /// root itself is always followed.
+    pub follow_symlinks: bool,
+    /// Deepest level visited; files directly under the root are at depth 1.
+    pub max_depth: Option<usize>,
+    /// Compute [`FileMeta::hash`] for every file.
+    pub hash: bool,
+    /// Files to skip, matched like [`DirGlobs`] against the path relative
+    /// to the walk root.
+    pub ignore: DirGlobs,
+    /// Skip protected directories instead of reporting their files with
+    /// [`FileMeta::protected`] set; see [`PROTECTED_MARKERS`].
+    pub skip_protected: bool,
+    /// Extensions to process exclusively; see [`ExtensionFilter`].
+    pub include_ext: Vec<String>,
+    /// Extensions to skip when `include_ext` is empty.
+    pub exclude_ext: Vec<String>,
+    /// Directories whose whole subtree is skipped; see [`DirGlobs`].
+    pub exclude_dirs: DirGlobs,
+    /// Folders, relative to the walk root, such as `uncategorized`; when
+    /// set, only files under one of them are processed.
+    pub only_under: Vec<PathBuf>,
+    /// Configured state directory, which is never walked; copied from the
+    /// top-level `state_dir` key. See [`StatePaths::new`].
+    #[serde(skip)]
+    pub state_dir: Option<PathBuf>,
+}
+
+impl Default for WalkOptions {
+    fn default() -> Self {
+        Self {
+            include_hidden: false,
+            follow_symlinks: false,
+            max_depth: None,
+            hash: true,
+            ignore: DirGlobs::default(),
+            skip_protected: false,
+            include_ext: Vec::new(),
+            exclude_ext: Vec::new(),
+            exclude_dirs: DirGlobs::default(),
+            only_under: Vec::new(),
+            state_dir: None,
+        }
+    }
+}
+
+impl WalkOptions {
+    pub fn extension_filter(&self) -> ExtensionFilter {
+        ExtensionFilter::new(&self.include_ext, &self.exclude_ext)
+    }
+
+    /// Whether a file at `relative`, a path relative to the walk root, lies
+    /// under one of the `only_under` folders.
+    pub fn is_only_under(&self, relative: &Path) -> bool {
+        let relative = without_cur_dir(relative);
+        self.only_under.is_empty()
+            || self
+                .only_under
+                .iter()
+                .any(|folder| relative.starts_with(without_cur_dir(folder)))
+    }
+
+    /// Whether the walk must enter the directory at `relative` to reach
+    /// files under the `only_under` folders.
+    pub fn may_contain_only_under(&self, relative: &Path) -> bool {
+        let relative = without_cur_dir(relative);
+        self.only_under.is_empty()
+            || self.only_under.iter().any(|folder| {
+                let folder = without_cur_dir(folder);
+                relative.starts_with(&folder) || folder.starts_with(&relative)
+            })
+    }
+}
+
+fn without_cur_dir(path: &Path) -> PathBuf {
+    path.components()
+        .filter(|c| !matches!(c, std::path::Component::CurDir))
+        .collect()
+}
+
+/// Compiled directory globs, such as `**/cache` or `Downloads/tmp`.
+///
+/// Patterns are matched against directory paths relative to the walk root,
+/// and `*` never crosses a `/`. A pattern without `/` matches a directory
+/// name at any depth. Invalid patterns are a config error.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(try_from = "Vec<String>")]
+pub struct DirGlobs {
+    patterns: Vec<String>,
+    set: GlobSet,
+}
+
+impl DirGlobs {
+    pub fn new<S: AsRef<str>>(patterns: &[S]) -> Result<Self, globset::Error> {
+        let mut builder = GlobSetBuilder::new();
+        for pattern in patterns {
+            let pattern = pattern.as_ref().trim_end_matches('/');
+            let pattern = if pattern.contains('/') {
+                pattern.to_string()
+            } else {
+                format!("**/{}", pattern)
+            };
+            builder.add(GlobBuilder::new(&pattern).literal_separator(true).build()?);
+        }
+        Ok(Self {
+            patterns: patterns.iter().map(|p| p.as_ref().to_string()).collect(),
+            set: builder.build()?,
+        })
+    }
+
+    pub fn patterns(&self) -> &[String] {
+        &self.patterns
+    }
+
+    /// Whether the directory at `relative`, relative to the walk root, is
+    /// pruned.
+    pub fn is_excluded(&self, relative: &Path) -> bool {
+        self.set.is_match(relative)
+    }
+}
+
+impl TryFrom<Vec<String>> for DirGlobs {
+    type Error = globset::Error;
+
+    fn try_from(patterns: Vec<String>) -> Result<Self, Self::Error> {
+        DirGlobs::new(&patterns)
+    }
+}
+
+/// Extensions of formats whose raw bytes are readable text.
+const TEXT_EXTENSIONS: &[&str] = &[
+    "txt", "md", "markdown", "rst", "csv", "tsv", "json", "yaml", "yml", "toml", "ini", "cfg",
+    "conf", "xml", "html", "htm", "log", "tex", "rs", "py", "js", "ts", "go", "java", "c", "h",
+    "cpp", "hpp", "rb", "sh",
+];
+
+/// Whether files with extension `ext` can be read as plain text.
+///
+/// The comparison ignores case and a leading dot.
+pub fn is_text_extension(ext: &str) -> bool {
+    TEXT_EXTENSIONS.contains(&normalize_extension(ext).as_str())
+}
+
+/// Lowercases an extension and strips any leading dot.
+pub fn normalize_extension(ext: &str) -> String {
+    ext.trim().trim_start_matches('.').to_lowercase()
+}
+
+/// Normalized extension of `path`, if it has one.
+pub fn extension_of(path: &Path) -> Option<String> {
+    path.extension()
+        .map(|e| normalize_extension(&e.to_string_lossy()))
+}
+
+/// Names Windows reserves regardless of extension.
+const RESERVED_NAMES: &[&str] = &[
+    "CON", "PRN", "AUX", "NUL", "COM1", "COM2", "COM3", "COM4", "COM5", "COM6", "COM7", "COM8",
+    "COM9", "LPT1", "LPT2", "LPT3", "LPT4", "LPT5", "LPT6", "LPT7", "LPT8", "LPT9",
+];
+
+/// Longest file name most filesystems accept, in bytes.
+const MAX_FILE_NAME_BYTES: usize = 255;
+
+/// Inner extensions kept together with the outer one, as in `.tar.gz`.
+const COMPOUND_EXTENSIONS: &[&str] = &["tar"];
+
+/// Makes a single path component safe on every supported filesystem.
+///
+/// Separators, control characters and characters Windows rejects are
+/// replaced with `_`, trailing dots and spaces are trimmed, and reserved
+/// device names such as `CON` are prefixed with `_`.
+pub fn sanitize_component(name: &str) -> String {
+    let replaced: String = name
+        .chars()
+        .map(|c| match c {
+            '/' | '\\' | '<' | '>' | ':' | '"' | '|' | '?' | '*' => '_',
+            c if c.is_control() => '_',
+            c => c,
+        })
+        .collect();
+
+    let trimmed = replaced.trim().trim_end_matches(['.', ' ']);
+    if trimmed.is_empty() || trimmed.chars().all(|c| c == '.') {
+        return "_".to_string();
+    }
+
+    let stem = trimmed.split('.').next().unwrap_or(trimmed).trim_end();
+    if RESERVED_NAMES.iter().any(|r| r.eq_ignore_ascii_case(stem)) {
+        return format!("_{}", trimmed);
+    }
+    trimmed.to_string()
+}
+
+/// Makes a file name safe like [`sanitize_component`], keeping its
+/// extension.
+///
+/// The stem and the extension are sanitized separately, so a reserved
+/// stem is caught whatever the extension. Names over 255 bytes are
+/// shortened in the stem, and compound extensions such as `.tar.gz` stay
+/// whole.
+pub fn sanitize_filename(name: &str) -> String {
+    let (stem, ext) = split_extension(name.trim());
+    let ext = ext.to_string();
+    let mut stem = sanitize_component(stem);
+
+    let budget = MAX_FILE_NAME_BYTES.saturating_sub(ext.len()).max(1);
+    if stem.len() > budget {
+        let mut end = budget;
+        while !stem.is_char_boundary(end) {
+            end -= 1;
+        }
+        stem = sanitize_component(&stem[..end]);
+    }
+    stem + &ext
+}
+
+/// File name for the `n`-th file colliding at a destination, such as
+/// `report (2).pdf`.
+pub fn collision_name(name: &str, n: usize) -> String {
+    let (stem, ext) = split_extension(name.trim());
+    sanitize_filename(&format!("{} ({}){}", stem, n, ext))
+}
+
+/// Composes a destination path from folder components and a file name,
+/// sanitizing each of them.
+pub fn destination_path<S: AsRef<str>>(base: &Path, folders: &[S], file_name: &str) -> PathBuf {
+    let mut path = base.to_path_buf();
+    for folder in folders {
+        path.push(sanitize_component(folder.as_ref()));
+    }
+    path.push(sanitize_filename(file_name));
+    path
+}
+
+/// Splits a file name into its stem and its extension, dot included.
+///
+/// Only a short alphanumeric suffix counts as an extension, and dotfiles
+/// such as `.bashrc` have none.
+fn split_extension(name: &str) -> (&str, &str) {
+    let is_extension = |ext: &str| {
+        !ext.is_empty() && ext.len() <= 10 && ext.chars().all(|c| c.is_ascii_alphanumeric())
+    };
+
+    let Some(dot) = name
+        .rfind('.')
+        .filter(|i| *i > 0 && is_extension(&name[i + 1..]))
+    else {
+        return (name, "");
+    };
+    let (stem, ext) = name.split_at(dot);
+    match stem.rfind('.') {
+        Some(inner)
+            if inner > 0
+                && COMPOUND_EXTENSIONS
+                    .iter()
+                    .any(|c| c.eq_ignore_ascii_case(&stem[inner + 1..])) =>
+        {
+            name.split_at(inner)
+        }
+        _ => (stem, ext),
+    }
+}
+
+/// Metadata of a file found by a walk.
+///
+/// Timestamps are kept to whole seconds and serialize as unix seconds, so
+/// a JSON dump reads back into an equal value.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FileMeta {
+    pub path: PathBuf,
+    /// Normalized extension; see [`extension_of`]. Extensionless files get
+    /// the one sniffed from their content.
+    pub extension: Option<String>,
+    pub size: u64,
+    #[serde(with = "unix_seconds")]
+    pub created: Option<SystemTime>,
+    #[serde(with = "unix_seconds")]
+    pub modified: Option<SystemTime>,
+    /// Content hash, when the walk computed one; see [`compute_file_hash`].
+    pub hash: Option<String>,
+    /// Whether the file lies inside a protected directory.
+    #[serde(default)]
+    pub protected: bool,
+}
+
+impl FileMeta {
+    /// Reads the metadata of the file at `path`, without hashing it.
+    pub fn from_path(path: &Path) -> io::Result<Self> {
+        let metadata = fs::metadata(path)?;
+        let extension = match extension_of(path) {
+            Some(extension) => Some(extension),
+            None if metadata.is_file() => sniff_file_extension(path)?.map(str::to_string),
+            None => None,
+        };
+        Ok(Self {
+            path: path.to_path_buf(),
+            extension,
+            size: metadata.len(),
+            created: metadata.created().ok().map(truncate_to_seconds),
+            modified: metadata.modified().ok().map(truncate_to_seconds),
+            hash: None,
+            protected: false,
+        })
+    }
+
+    pub fn to_json(&self) -> serde_json::Result<String> {
+        serde_json::to_string_pretty(self)
+    }
+
+    pub fn from_json(json: &str) -> serde_json::Result<Self> {
+        serde_json::from_str(json)
+    }
+}
+
+fn truncate_to_seconds(time: SystemTime) -> SystemTime {
+    match time.duration_since(UNIX_EPOCH) {
+        Ok(after) => UNIX_EPOCH + Duration::from_secs(after.as_secs()),
+        Err(before) => UNIX_EPOCH - Duration::from_secs(before.duration().as_secs()),
+    }
+}
+
+/// Serializes an optional `SystemTime` as signed unix seconds.
+mod unix_seconds {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(time: &Option<SystemTime>, s: S) -> Result<S::Ok, S::Error> {
+        time.map(|t| match t.duration_since(UNIX_EPOCH) {
+            Ok(after) => after.as_secs() as i64,
+            Err(before) => -(before.duration().as_secs() as i64),
+        })
+        .serialize(s)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(d: D) -> Result<Option<SystemTime>, D::Error> {
+        Ok(Option::<i64>::deserialize(d)?.map(|secs| {
+            let offset = Duration::from_secs(secs.unsigned_abs());
+            if secs < 0 {
+                UNIX_EPOCH - offset
+            } else {
+                UNIX_EPOCH + offset
+            }
+        }))
+    }
+}
+
+/// Default name of the state directory created under the base directory.
+pub const STATE_DIR_NAME: &str = ".cognifs";
+
+/// Standard locations of every persistent artifact: checkpoints, undo
+/// logs, caches and exported plans.
+///
+/// Nothing is created on construction; [`Self::ensure_file_parent`] and
+/// [`Self::ensure_dir`] create directories right before they are written.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StatePaths {
+    root: PathBuf,
+}
+
+impl StatePaths {
+    /// Resolves the state directory for `base`.
+    ///
+    /// A configured `state_dir` is used as is when absolute, such as a
+    /// per-user data directory, and relative to `base` otherwise; without
+    /// one, `base/.cognifs` is used.
+    pub fn new(base: &Path, state_dir: Option<&Path>) -> Self {
+        let root = match state_dir {
+            Some(dir) if dir.is_absolute() => dir.to_path_buf(),
+            Some(dir) => base.join(dir),
+            None => base.join(STATE_DIR_NAME),
+        };
+        Self { root }
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    pub fn index_checkpoint(&self) -> PathBuf {
+        self.root.join(crate::checkpoint::CHECKPOINT_FILE_NAME)
+    }
+
+    pub fn undo_log(&self) -> PathBuf {
+        self.root.join("undo.log")
+    }
+
+    pub fn plans_dir(&self) -> PathBuf {
+        self.root.join("plans")
+    }
+
+    pub fn cache_dir(&self) -> PathBuf {
+        self.root.join("cache")
+    }
+
+    pub fn embeddings_cache_dir(&self) -> PathBuf {
+        self.cache_dir().join("embeddings")
+    }
+
+    /// Creates the directory that will hold the file at `path` and returns
+    /// `path`.
+    pub fn ensure_file_parent(&self, path: PathBuf) -> io::Result<PathBuf> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        Ok(path)
+    }
+
+    /// Creates the directory at `dir`, such as [`Self::plans_dir`], and
+    /// returns it.
+    pub fn ensure_dir(&self, dir: PathBuf) -> io::Result<PathBuf> {
+        fs::create_dir_all(&dir)?;
+        Ok(dir)
+    }
+
+    /// Whether `path` is or lies inside the state directory, or inside any
+    /// directory named [`STATE_DIR_NAME`].
+    pub fn contains(&self, path: &Path) -> bool {
+        path.starts_with(&self.root) || path.components().any(|c| c.as_os_str() == STATE_DIR_NAME)
+    }
+}
+
+/// Entries whose presence marks a directory as a project or repository
+/// that should not be reorganized.
+pub const PROTECTED_MARKERS: &[&str] = &[
+    ".git",
+    ".hg",
+    ".svn",
+    "Cargo.toml",
+    "package.json",
+    "pyproject.toml",
+    "go.mod",
+];
+
+/// Hex-encoded BLAKE3 hash of the file at `path`, read in a stream.
+pub fn compute_file_hash(path: &Path) -> io::Result<String> {
+    let mut hasher = blake3::Hasher::new();
+    hasher.update_reader(File::open(path)?)?;
+    Ok(hasher.finalize().to_hex().to_string())
+}
+
+/// Walks `root` depth-first, yielding the [`FileMeta`] of every regular
+/// file in sorted order.
+///
+/// Hidden entries, symbolic links, depth, the state directory, protected
+/// directories, ignore globs and the `[walk]` filters are all applied here
+/// so every caller traverses the same way. An error on one entry is
+/// yielded without ending the walk or dropping its siblings.
+pub fn walk_files(root: &Path, options: &WalkOptions) -> WalkFiles {
+    WalkFiles {
+        root: root.to_path_buf(),
+        state: StatePaths::new(root, options.state_dir.as_deref()),
+        stack: vec![(root.to_path_buf(), 0, false)],
+        errors: VecDeque::new(),
+        options: options.clone(),
+        extensions: options.extension_filter(),
+        visited_dirs: HashSet::new(),
+    }
+}
+
+/// Iterator returned by [`walk_files`].
+#[derive(Debug)]
+pub struct WalkFiles {
+    root: PathBuf,
+    state: StatePaths,
+    /// Paths still to visit, with their depth and inherited protection.
+    stack: Vec<(PathBuf, usize, bool)>,
+    errors: VecDeque<io::Error>,
+    options: WalkOptions,
+    extensions: ExtensionFilter,
+    visited_dirs: HashSet<PathBuf>,
+}
+
+impl WalkFiles {
+    fn relative<'a>(&self, path: &'a Path) -> &'a Path {
+        path.strip_prefix(&self.root).unwrap_or(path)
+    }
+
+    fn is_hidden(&self, path: &Path) -> bool {
+        !self.options.include_hidden
+            && path
+                .file_name()
+                .is_some_and(|n| n.to_string_lossy().starts_with('.'))
+    }
+
+    fn enters_dir(&self, path: &Path, depth: usize) -> bool {
+        let relative = self.relative(path);
+        depth == 0
+            || (!self.is_hidden(path)
+                && !self.state.contains(path)
+                && !self.options.exclude_dirs.is_excluded(relative)
+                && self.options.may_contain_only_under(relative))
+    }
+
+    fn keeps_file(&self, path: &Path) -> bool {
+        let relative = self.relative(path);
+        !self.is_hidden(path)
+            && !self.options.ignore.is_excluded(relative)
+            && self.options.is_only_under(relative)
+            && self.extensions.matches(path)
+    }
+
+    fn push_children(&mut self, dir: &Path, depth: usize, protected: bool) -> io::Result<()> {
+        if self.options.follow_symlinks {
+            // Symlinked directories can form cycles; visit each target once.
+            if !self.visited_dirs.insert(dir.canonicalize()?) {
+                return Ok(());
+            }
+        }
+
+        let mut children = Vec::new();
+        for entry in fs::read_dir(dir)? {
+            match entry {
+                Ok(entry) => children.push(entry.path()),
+                Err(e) => self.errors.push_back(e),
+            }
+        }
+        children.sort();
+        self.stack.extend(
+            children
+                .into_iter()
+                .rev()
+                .map(|path| (path, depth + 1, protected)),
+        );
+        Ok(())
+    }
+
+    fn file_meta(&self, path: &Path, protected: bool) -> io::Result<FileMeta> {
+        let mut meta = FileMeta::from_path(path)?;
+        if self.options.hash {
+            meta.hash = Some(compute_file_hash(path)?);
+        }
+        meta.protected = protected;
+        Ok(meta)
+    }
+}
+
+fn is_protected_dir(dir: &Path) -> bool {
+    PROTECTED_MARKERS
+        .iter()
+        .any(|marker| fs::symlink_metadata(dir.join(marker)).is_ok())
+}
+
+impl Iterator for WalkFiles {
+    type Item = io::Result<FileMeta>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(e) = self.errors.pop_front() {
+                return Some(Err(e));
+            }
+            let (path, depth, protected) = self.stack.pop()?;
+
+            let metadata = match fs::symlink_metadata(&path) {
+                Ok(m)
+                    if m.file_type().is_symlink() && depth > 0 && !self.options.follow_symlinks =>
+                {
+                    continue
+                }
+                Ok(m) if m.file_type().is_symlink() => match fs::metadata(&path) {
+                    Ok(target) => target,
+                    Err(e) => return Some(Err(e)),
+                },
+                Ok(m) => m,
+                Err(e) => return Some(Err(e)),
+            };
+
+            if metadata.is_dir() {
+                if !self.enters_dir(&path, depth)
+                    || self.options.max_depth.is_some_and(|max| depth >= max)
+                {
+                    continue;
+                }
+                let protected = protected || (depth > 0 && is_protected_dir(&path));
+                if protected && self.options.skip_protected {
+                    continue;
+                }
+                if let Err(e) = self.push_children(&path, depth, protected) {
+                    return Some(Err(e));
+                }
+            } else if metadata.is_file() && self.keeps_file(&path) {
+                return Some(self.file_meta(&path, protected));
+            }
+        }
+    }
+}
+
+/// Text extraction limits, loaded from the `[extraction]` section.
+///
+/// A zero `log_sample_bytes` would sample nothing, so it is rejected when
+/// the config is loaded.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(try_from = "RawExtractionConfig")]
+pub struct ExtractionConfig {
+    log_sample_bytes: u64,
+    sample_threshold_bytes: u64,
+}
+
+#[derive(Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct RawExtractionConfig {
+    log_sample_bytes: u64,
+    sample_threshold_bytes: u64,
+}
+
+impl Default for RawExtractionConfig {
+    fn default() -> Self {
+        Self {
+            log_sample_bytes: 64 * 1024,
+            sample_threshold_bytes: 10 * 1024 * 1024,
+        }
+    }
+}
+
+impl Default for ExtractionConfig {
+    fn default() -> Self {
+        let raw = RawExtractionConfig::default();
+        Self {
+            log_sample_bytes: raw.log_sample_bytes,
+            sample_threshold_bytes: raw.sample_threshold_bytes,
+        }
+    }
+}
+
+impl TryFrom<RawExtractionConfig> for ExtractionConfig {
+    type Error = String;
+
+    fn try_from(raw: RawExtractionConfig) -> Result<Self, Self::Error> {
+        Self::new(raw.log_sample_bytes, raw.sample_threshold_bytes)
+    }
+}
+
+impl ExtractionConfig {
+    pub fn new(log_sample_bytes: u64, sample_threshold_bytes: u64) -> Result<Self, String> {
+        if log_sample_bytes == 0 {
+            return Err("log_sample_bytes must be greater than 0".to_string());
+        }
+        Ok(Self {
+            log_sample_bytes,
+            sample_threshold_bytes,
+        })
+    }
+
+    /// Bytes kept from each end of a sampled file.
+    pub fn log_sample_bytes(&self) -> u64 {
+        self.log_sample_bytes
+    }
+
+    /// Size above which any text file is sampled rather than read whole.
+    pub fn sample_threshold_bytes(&self) -> u64 {
+        self.sample_threshold_bytes
+    }
+
+    /// Reads a text file, sampling logs and oversized files, along with
+    /// the tags its content earns.
+    ///
+    /// Binary content yields empty text and [`BINARY_TAG`]; see
+    /// [`text_or_binary`].
+    pub fn read_text(&self, path: &Path) -> io::Result<(String, Vec<String>)> {
+        let size = fs::metadata(path)?.len();
+        if !needs_sampling(path, size, self.sample_threshold_bytes) {
+            return Ok(text_or_binary(&fs::read(path)?));
+        }
+        let mut head = Vec::with_capacity(BINARY_SNIFF_BYTES);
+        File::open(path)?
+            .take(BINARY_SNIFF_BYTES as u64)
+            .read_to_end(&mut head)?;
+        if looks_binary(&head) {
+            return Ok(text_or_binary(&head));
+        }
+        Ok((read_head_tail(path, self.log_sample_bytes)?, Vec::new()))
+    }
+}
+
+/// Whether a text file should be sampled rather than read in full.
+///
+/// Log files are always sampled; other files only above `threshold` bytes.
+pub fn needs_sampling(path: &Path, size: u64, threshold: u64) -> bool {
+    extension_of(path).as_deref() == Some("log") || size > threshold
+}
+
+/// Reads at most `sample_bytes` from the start and from the end of a file.
+///
+/// Files no larger than twice the sample are read whole. Invalid UTF-8 at
+/// the cut points is replaced rather than rejected.
+pub fn read_head_tail(path: &Path, sample_bytes: u64) -> io::Result<String> {
+    let mut file = File::open(path)?;
+    let size = file.metadata()?.len();
+    let whole_limit = sample_bytes.saturating_mul(2);
+
+    if size <= whole_limit {
+        let mut buf = Vec::with_capacity(prealloc_len(size));
+        file.take(whole_limit).read_to_end(&mut buf)?;
+        return Ok(String::from_utf8_lossy(&buf).into_owned());
+    }
+
+    let mut head = Vec::with_capacity(prealloc_len(sample_bytes));
+    (&mut file).take(sample_bytes).read_to_end(&mut head)?;
+
+    let mut tail = Vec::with_capacity(prealloc_len(sample_bytes));
+    file.seek(SeekFrom::Start(size - sample_bytes))?;
+    file.take(sample_bytes).read_to_end(&mut tail)?;
+
+    Ok(format!(
+        "{}\n...\n{}",
+        String::from_utf8_lossy(&head),
+        String::from_utf8_lossy(&tail)
+    ))
+}
+
+/// Upper bound on buffer preallocation, whatever the reported file size.
+const MAX_PREALLOC_BYTES: u64 = 1024 * 1024;
+
+fn prealloc_len(len: u64) -> usize {
+    len.min(MAX_PREALLOC_BYTES) as usize
+}
+
+/// Number of leading bytes inspected by [`looks_binary`].
+const BINARY_SNIFF_BYTES: usize = 8192;
+
+/// Tag given to files whose content is not text.
+pub const BINARY_TAG: &str = "binary";
+
+/// Heuristically decides whether `bytes` hold binary rather than text data.
+///
+/// Only the first few KiB are inspected. Content is binary when more than
+/// 1% of it is NUL bytes, or when over 30% is control characters or
+/// invalid UTF-8. Content starting with a UTF-16 byte order mark is
+/// decoded as UTF-16 instead, since its NUL bytes are expected there.
+pub fn looks_binary(bytes: &[u8]) -> bool {
+    let sample = &bytes[..bytes.len().min(BINARY_SNIFF_BYTES)];
+    let text = match decode_utf16_with_bom(sample) {
+        Some(text) => text,
+        None => {
+            let nul = sample.iter().filter(|b| **b == 0).count();
+            if nul * 100 > sample.len() {
+                return true;
+            }
+            // A multi-byte character cut by the end of the sample is fine.
+            let sample = match std::str::from_utf8(sample) {
+                Err(e) if e.error_len().is_none() => &sample[..e.valid_up_to()],
+                _ => sample,
+            };
+            String::from_utf8_lossy(sample).into_owned()
+        }
+    };
+
+    let total = text.chars().count();
+    let suspicious = text
+        .chars()
+        .filter(|c| {
+            *c == char::REPLACEMENT_CHARACTER
+                || (c.is_control() && !matches!(c, '\n' | '\r' | '\t' | '\x0c'))
+        })
+        .count();
+    suspicious * 10 > total * 3
+}
+
+/// Decodes file content as text, or flags it as binary.
+///
+/// Binary content yields empty text and the [`BINARY_TAG`] tag, so the
+/// embedding falls back to the file name instead of garbage.
+pub fn text_or_binary(bytes: &[u8]) -> (String, Vec<String>) {
+    if looks_binary(bytes) {
+        return (String::new(), vec![BINARY_TAG.to_string()]);
+    }
+    let text =
+        decode_utf16_with_bom(bytes).unwrap_or_else(|| String::from_utf8_lossy(bytes).into_owned());
+    (text, Vec::new())
+}
+
+/// Decodes `bytes` as UTF-16 if they start with a byte order mark.
+fn decode_utf16_with_bom(bytes: &[u8]) -> Option<String> {
+    let (body, little_endian) = match bytes {
+        [0xFF, 0xFE, rest @ ..] => (rest, true),
+        [0xFE, 0xFF, rest @ ..] => (rest, false),
+        _ => return None,
+    };
+    let units = body.chunks_exact(2).map(|pair| {
+        if little_endian {
+            u16::from_le_bytes([pair[0], pair[1]])
+        } else {
+            u16::from_be_bytes([pair[0], pair[1]])
+        }
+    });
+    Some(
+        char::decode_utf16(units)
+            .map(|c| c.unwrap_or(char::REPLACEMENT_CHARACTER))
+            .collect(),
+    )
+}
+
+/// Tag given to files that cannot be read without a password.
+pub const ENCRYPTED_TAG: &str = "encrypted";
+
+/// Tag given to files whose content does not parse as their format.
+pub const CORRUPT_TAG: &str = "corrupt";
+
+/// Tag given to files no extractor handles.
+pub const UNSUPPORTED_TAG: &str = "unsupported";
+
+/// Bytes read from each end of a file to look for encryption markers.
+const ENCRYPTION_SCAN_BYTES: u64 = 64 * 1024;
+
+/// Failure to extract the content of a file, classified so callers can tag
+/// the file and decide whether to retry.
+#[derive(Debug)]
+pub enum ExtractionError {
+    Io(io::Error),
+    /// The file is password-protected; tag it [`ENCRYPTED_TAG`] and skip
+    /// embedding rather than reporting a parse error.
+    Encrypted,
+    /// No extractor handles the format.
+    Unsupported,
+    /// The content does not parse as its format.
+    Corrupt(String),
+    /// Extraction did not finish in time.
+    Timeout,
+}
+
+impl ExtractionError {
+    /// Tag recording the failure on the file, for failures that describe
+    /// the file itself rather than the run.
+    pub fn tag(&self) -> Option<&'static str> {
+        match self {
+            ExtractionError::Encrypted => Some(ENCRYPTED_TAG),
+            ExtractionError::Unsupported => Some(UNSUPPORTED_TAG),
+            ExtractionError::Corrupt(_) => Some(CORRUPT_TAG),
+            ExtractionError::Io(_) | ExtractionError::Timeout => None,
+        }
+    }
+
+    /// Whether extracting the same file again may succeed.
+    pub fn is_retryable(&self) -> bool {
+        matches!(self, ExtractionError::Io(_) | ExtractionError::Timeout)
+    }
+}
+
+impl fmt::Display for ExtractionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ExtractionError::Io(e) => write!(f, "cannot read file: {}", e),
+            ExtractionError::Encrypted => write!(f, "file is encrypted"),
+            ExtractionError::Unsupported => write!(f, "unsupported file format"),
+            ExtractionError::Corrupt(reason) => write!(f, "corrupt file: {}", reason),
+            ExtractionError::Timeout => write!(f, "extraction timed out"),
+        }
+    }
+}
+
+impl std::error::Error for ExtractionError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ExtractionError::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+/// Classifies I/O failures: timeouts, invalid or truncated data and
+/// unsupported operations get their own variant.
+impl From<io::Error> for ExtractionError {
+    fn from(e: io::Error) -> Self {
+        match e.kind() {
+            io::ErrorKind::TimedOut => ExtractionError::Timeout,
+            io::ErrorKind::InvalidData | io::ErrorKind::UnexpectedEof => {
+                ExtractionError::Corrupt(e.to_string())
+            }
+            io::ErrorKind::Unsupported => ExtractionError::Unsupported,
+            _ => ExtractionError::Io(e),
+        }
+    }
+}
+
+/// Fails with [`ExtractionError::Encrypted`] when the file at `path` is an
+/// encrypted PDF, a zip with encrypted entries, or an encrypted Office
+/// document.
+///
+/// The format is recognized from the content, and only both ends of the
+/// file are read.
+pub fn check_not_encrypted(path: &Path) -> Result<(), ExtractionError> {
+    let mut file = File::open(path)?;
+    let size = file.metadata()?.len();
+
+    let mut head = Vec::with_capacity(prealloc_len(ENCRYPTION_SCAN_BYTES));
+    (&mut file)
+        .take(ENCRYPTION_SCAN_BYTES)
+        .read_to_end(&mut head)?;
+    let mut tail = Vec::new();
+    if size > ENCRYPTION_SCAN_BYTES {
+        let start = (size - ENCRYPTION_SCAN_BYTES).max(ENCRYPTION_SCAN_BYTES);
+        file.seek(SeekFrom::Start(start))?;
+        file.take(ENCRYPTION_SCAN_BYTES).read_to_end(&mut tail)?;
+    }
+
+    if is_encrypted(&head, &tail) {
+        Err(ExtractionError::Encrypted)
+    } else {
+        Ok(())
+    }
+}
+
+fn is_encrypted(head: &[u8], tail: &[u8]) -> bool {
+    const CFB_MAGIC: &[u8] = b"\xD0\xCF\x11\xE0\xA1\xB1\x1A\xE1";
+
+    if head.starts_with(b"%PDF-") {
+        return [head, tail].iter().any(|part| contains_pdf_encrypt(part));
+    }
+    if head.starts_with(b"PK\x03\x04") {
+        return zip_has_encrypted_entry(head, tail);
+    }
+    if head.starts_with(CFB_MAGIC) {
+        // Encrypted OOXML is a compound file holding an EncryptionInfo stream.
+        let marker: Vec<u8> = "EncryptionInfo"
+            .encode_utf16()
+            .flat_map(u16::to_le_bytes)
+            .collect();
+        return [head, tail]
+            .iter()
+            .any(|part| find_bytes(part, &marker).is_some());
+    }
+    false
+}
+
+fn contains_pdf_encrypt(bytes: &[u8]) -> bool {
+    let mut rest = bytes;
+    while let Some(i) = find_bytes(rest, b"/Encrypt") {
+        let after = rest.get(i + b"/Encrypt".len()).copied();
+        if !after.is_some_and(|b| b.is_ascii_alphanumeric()) {
+            return true;
+        }
+        rest = &rest[i + 1..];
+    }
+    false
+}
+
+/// Whether a local or central directory header has the encryption flag.
+fn zip_has_encrypted_entry(head: &[u8], tail: &[u8]) -> bool {
+    let flagged = |bytes: &[u8], signature: &[u8], flags_offset: usize| {
+        let mut rest = bytes;
+        while let Some(i) = find_bytes(rest, signature) {
+            if rest
+                .get(i + flags_offset)
+                .is_some_and(|flags| flags & 1 == 1)
+            {
+                return true;
+            }
+            rest = &rest[i + 1..];
+        }
+        false
+    };
+    [head, tail]
+        .iter()
+        .any(|part| flagged(part, b"PK\x03\x04", 6) || flagged(part, b"PK\x01\x02", 8))
+}
+
+fn find_bytes(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack
+        .windows(needle.len())
+        .position(|window| window == needle)
+}
+
+/// Bytes read from an extensionless file to sniff its type.
+const SNIFF_HEADER_BYTES: u64 = 8192;
+
+/// Guesses a file extension from the first bytes of its content.
+///
+/// Formats known to `infer` win, down to the ISO media brand, so HEIC,
+/// AVIF, M4A and MOV files are told apart from MP4. Otherwise content that
+/// is valid UTF-8 without NUL bytes is reported as `txt`.
+pub fn sniff_extension(header: &[u8]) -> Option<&'static str> {
+    if let Some(kind) = infer::get(header) {
+        return Some(kind.extension());
+    }
+    (!header.is_empty() && !looks_binary(header)).then_some("txt")
+}
+
+/// Sniffs the extension of the file at `path` from its first few KiB.
+pub fn sniff_file_extension(path: &Path) -> io::Result<Option<&'static str>> {
+    let mut header = Vec::with_capacity(SNIFF_HEADER_BYTES as usize);
+    File::open(path)?
+        .take(SNIFF_HEADER_BYTES)
+        .read_to_end(&mut header)?;
+    Ok(sniff_extension(&header))
+}
+
+#[test]
+fn test_is_text_extension() {
+    assert!(is_text_extension("md"));
+    assert!(is_text_extension(".TXT"));
+    assert!(!is_text_extension("pdf"));
+    assert!(!is_text_extension(""));
+}
+
+#[test]
+fn test_extension_of_normalizes() {
+    assert_eq!(
+        extension_of(Path::new("a/Report.PDF")),
+        Some("pdf".to_string())
+    );
+    assert_eq!(extension_of(Path::new("Makefile")), None);
+    assert_eq!(normalize_extension(" .Tar "), "tar");
+}
+
+#[test]
+fn test_extension_filter_include_only() {
+    let filter = ExtensionFilter::new(&[".PDF", "md"], &[]);
+    assert!(filter.matches(Path::new("a/report.pdf")));
+    assert!(filter.matches(Path::new("notes.MD")));
+    assert!(!filter.matches(Path::new("photo.jpg")));
+    assert!(!filter.matches(Path::new("Makefile")));
+}
+
+#[test]
+fn test_extension_filter_exclude_only() {
+    let filter = ExtensionFilter::new(&[], &["jpg", ".PNG"]);
+    assert!(!filter.matches(Path::new("photo.JPG")));
+    assert!(!filter.matches(Path::new("icon.png")));
+    assert!(filter.matches(Path::new("report.pdf")));
+    assert!(filter.matches(Path::new("Makefile")));
+}
+
+#[test]
+fn test_extension_filter_include_wins_over_exclude() {
+    let filter = ExtensionFilter::new(&["pdf"], &["pdf", "jpg"]);
+    assert!(filter.matches(Path::new("report.pdf")));
+    assert!(!filter.matches(Path::new("photo.jpg")));
+    assert!(!filter.matches(Path::new("notes.txt")));
+}
+
+#[test]
+fn test_extension_filter_default_accepts_everything() {
+    let filter = ExtensionFilter::default();
+    assert!(filter.matches(Path::new("anything.bin")));
+    assert!(filter.matches(Path::new("README")));
+}
+
+#[test]
+fn test_dir_globs_prune_matching_dirs_only() {
+    let globs = DirGlobs::new(&["**/cache", "Downloads/tmp", "node_modules"]).unwrap();
+    assert!(globs.is_excluded(Path::new("cache")));
+    assert!(globs.is_excluded(Path::new("project/cache")));
+    assert!(!globs.is_excluded(Path::new("project/cached")));
+    assert!(globs.is_excluded(Path::new("Downloads/tmp")));
+    assert!(!globs.is_excluded(Path::new("Other/tmp")));
+    assert!(!globs.is_excluded(Path::new("Downloads")));
+    assert!(globs.is_excluded(Path::new("web/app/node_modules")));
+}
+
+#[test]
+fn test_dir_globs_reject_invalid_patterns() {
+    assert!(DirGlobs::new(&["a/[b"]).is_err());
+    assert!(!DirGlobs::default().is_excluded(Path::new("anything")));
+}
+
+#[test]
+fn test_sanitize_component_strips_separators() {
+    assert_eq!(sanitize_component("a/b\\c"), "a_b_c");
+    assert_eq!(sanitize_component("nul\0byte"), "nul_byte");
+    assert_eq!(sanitize_component("what?: *"), "what__ _");
+    assert_eq!(sanitize_component("../"), ".._");
+}
+
+#[test]
+fn test_sanitize_component_reserved_names() {
+    assert_eq!(sanitize_component("CON"), "_CON");
+    assert_eq!(sanitize_component("aux"), "_aux");
+    assert_eq!(sanitize_component("com1.txt"), "_com1.txt");
+    assert_eq!(sanitize_component("CON .txt"), "_CON .txt");
+    assert_eq!(sanitize_component("console"), "console");
+}
+
+#[test]
+fn test_sanitize_component_trailing_dots_and_empty() {
+    assert_eq!(sanitize_component("report. "), "report");
+    assert_eq!(sanitize_component(".."), "_");
+    assert_eq!(sanitize_component(""), "_");
+    assert_eq!(sanitize_component("invoice (2).pdf"), "invoice (2).pdf");
+}
+
+#[test]
+fn test_sanitize_filename_keeps_extension() {
+    assert_eq!(sanitize_filename("CON.txt"), "_CON.txt");
+    assert_eq!(sanitize_filename("aux.tar.gz"), "_aux.tar.gz");
+    assert_eq!(sanitize_filename("a:b/c.pdf"), "a_b_c.pdf");
+    assert_eq!(sanitize_filename("notes."), "notes");
+    assert_eq!(sanitize_filename(".bashrc"), ".bashrc");
+    assert_eq!(sanitize_filename("v1.2 final"), "v1.2 final");
+
+    let long = format!("{}.pdf", "x".repeat(300));
+    let sanitized = sanitize_filename(&long);
+    assert_eq!(sanitized.len(), 255);
+    assert!(sanitized.ends_with("x.pdf"));
+}
+
+#[test]
+fn test_collision_name_suffixes_the_stem() {
+    assert_eq!(collision_name("report.pdf", 2), "report (2).pdf");
+    assert_eq!(collision_name("backup.tar.gz", 3), "backup (3).tar.gz");
+    assert_eq!(collision_name("Makefile", 2), "Makefile (2)");
+}
+
+#[test]
+fn test_destination_path_sanitizes_components() {
+    let path = destination_path(Path::new("/data"), &["Finance/2024", "aux"], "invoice?.pdf");
+    assert_eq!(path, Path::new("/data/Finance_2024/_aux/invoice_.pdf"));
+}
+
+#[test]
+fn test_file_meta_json_round_trip() {
+    let dir = std::env::temp_dir().join(format!("cognify-meta-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let with_ext = dir.join("Report.PDF");
+    let without_ext = dir.join("Makefile");
+    fs::write(&with_ext, b"%PDF-1.7").unwrap();
+    fs::write(&without_ext, b"all:").unwrap();
+
+    for path in [&with_ext, &without_ext] {
+        let meta = FileMeta::from_path(path).unwrap();
+        let json = meta.to_json().unwrap();
+        assert_eq!(FileMeta::from_json(&json).unwrap(), meta);
+    }
+    let meta = FileMeta::from_path(&without_ext).unwrap();
+    assert_eq!(meta.extension.as_deref(), Some("txt"));
+    assert_eq!(meta.size, 4);
+    assert_eq!(
+        FileMeta::from_path(&with_ext).unwrap().extension.as_deref(),
+        Some("pdf")
+    );
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_file_meta_timestamps_as_unix_seconds() {
+    let meta = FileMeta {
+        path: PathBuf::from("old"),
+        extension: None,
+        size: 0,
+        created: None,
+        modified: Some(UNIX_EPOCH - Duration::from_secs(86_400)),
+        hash: None,
+        protected: false,
+    };
+    let json = meta.to_json().unwrap();
+    assert!(json.contains("\"modified\": -86400"));
+    assert!(json.contains("\"created\": null"));
+    assert!(json.contains("\"extension\": null"));
+    assert_eq!(FileMeta::from_json(&json).unwrap(), meta);
+}
+
+#[test]
+fn test_extraction_config_samples_logs() {
+    let dir = std::env::temp_dir().join(format!("cognify-extract-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let log = dir.join("server.log");
+    let mut content = "start\n".to_string();
+    content.push_str(&"GET /index.html 200\n".repeat(5_000));
+    content.push_str("end\n");
+    fs::write(&log, &content).unwrap();
+    let notes = dir.join("notes.txt");
+    fs::write(&notes, "x".repeat(1_000)).unwrap();
+
+    let config = ExtractionConfig::new(100, 10 * 1024 * 1024).unwrap();
+    let (text, tags) = config.read_text(&log).unwrap();
+    assert!(tags.is_empty());
+    assert_eq!(text.len(), 100 * 2 + "\n...\n".len());
+    assert!(text.starts_with("start\n"));
+    assert!(text.ends_with("end\n"));
+    assert_eq!(config.read_text(&notes).unwrap().0.len(), 1_000);
+
+    let small = ExtractionConfig::new(100, 500).unwrap();
+    assert_eq!(small.read_text(&notes).unwrap().0.len(), 205);
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_extraction_config_rejects_zero_sample() {
+    assert!(ExtractionConfig::new(0, 1024).is_err());
+    assert_eq!(ExtractionConfig::default().log_sample_bytes(), 64 * 1024);
+}
+
+#[test]
+fn test_check_not_encrypted_detects_protected_files() {
+    let dir = std::env::temp_dir().join(format!("cognify-encrypted-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+
+    let zip_entry = |flags: u8| {
+        let mut bytes = b"PK\x03\x04\x14\x00".to_vec();
+        bytes.extend([flags, 0x00]);
+        bytes.extend([0u8; 22]);
+        bytes.extend(b"secret.txt");
+        bytes
+    };
+    let cfb = |stream: &str| {
+        let mut bytes = b"\xD0\xCF\x11\xE0\xA1\xB1\x1A\xE1".to_vec();
+        bytes.extend([0u8; 504]);
+        bytes.extend(stream.encode_utf16().flat_map(u16::to_le_bytes));
+        bytes
+    };
+    let mut long_pdf = b"%PDF-1.7\n".to_vec();
+    long_pdf.extend(vec![b' '; 200 * 1024]);
+    long_pdf.extend(b"trailer\n<< /Root 1 0 R /Encrypt 5 0 R >>\n%%EOF");
+
+    let cases: Vec<(&str, Vec<u8>, bool)> = vec![
+        (
+            "plain.pdf",
+            b"%PDF-1.4\n/EncryptMetadata false".to_vec(),
+            false,
+        ),
+        ("locked.pdf", long_pdf, true),
+        ("plain.zip", zip_entry(0x00), false),
+        ("locked.zip", zip_entry(0x01), true),
+        ("locked.docx", cfb("EncryptionInfo"), true),
+        ("legacy.doc", cfb("WordDocument"), false),
+        ("notes.txt", b"/Encrypt is just text here".to_vec(), false),
+    ];
+    for (name, bytes, encrypted) in cases {
+        let path = dir.join(name);
+        fs::write(&path, bytes).unwrap();
+        let result = check_not_encrypted(&path);
+        assert_eq!(
+            matches!(result, Err(ExtractionError::Encrypted)),
+            encrypted,
+            "{name}"
+        );
+    }
+    assert!(matches!(
+        check_not_encrypted(&dir.join("missing.pdf")),
+        Err(ExtractionError::Io(_))
+    ));
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_sniff_extension_by_content() {
+    let ftyp = |brand: &[u8]| {
+        let mut header = b"\x00\x00\x00\x18ftyp".to_vec();
+        header.extend(brand);
+        header.extend([0u8; 12]);
+        header
+    };
+    assert_eq!(sniff_extension(b"%PDF-1.7\n"), Some("pdf"));
+    assert_eq!(
+        sniff_extension(b"\x89PNG\r\n\x1a\n\x00\x00\x00\rIHDR"),
+        Some("png")
+    );
+    assert_eq!(sniff_extension(&ftyp(b"isom")), Some("mp4"));
+    assert_eq!(sniff_extension(&ftyp(b"heic")), Some("heif"));
+    assert_eq!(sniff_extension(&ftyp(b"avif")), Some("avif"));
+    assert_eq!(sniff_extension(&ftyp(b"M4A ")), Some("m4a"));
+    assert_eq!(sniff_extension(&ftyp(b"qt  ")), Some("mov"));
+    assert_eq!(sniff_extension("caf\u{e9} notes".as_bytes()), Some("txt"));
+    assert_eq!(sniff_extension(&"\u{e9}".as_bytes()[..1]), Some("txt"));
+    assert_eq!(sniff_extension(b"\x00\x01\x02\x03"), None);
+    assert_eq!(sniff_extension(b""), None);
+}
+
+#[test]
+fn test_file_meta_sniffs_extensionless_files() {
+    let dir = std::env::temp_dir().join(format!("cognify-sniff-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(dir.join("scan"), b"%PDF-1.7\n%binary").unwrap();
+    fs::write(dir.join("image"), b"\x89PNG\r\n\x1a\n\x00\x00\x00\rIHDR").unwrap();
+    fs::write(dir.join("README"), b"plain text notes\n").unwrap();
+    fs::write(dir.join("notes.md"), b"%PDF-1.7\n").unwrap();
+
+    let extension = |name: &str| FileMeta::from_path(&dir.join(name)).unwrap().extension;
+    assert_eq!(extension("scan").as_deref(), Some("pdf"));
+    assert_eq!(extension("image").as_deref(), Some("png"));
+    assert_eq!(extension("README").as_deref(), Some("txt"));
+    assert_eq!(extension("notes.md").as_deref(), Some("md"));
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_only_under_restricts_to_prefixes() {
+    let options = WalkOptions {
+        only_under: vec![
+            PathBuf::from("uncategorized"),
+            PathBuf::from("./a/unsorted"),
+        ],
+        ..WalkOptions::default()
+    };
+    assert!(options.is_only_under(Path::new("uncategorized/scan.pdf")));
+    assert!(options.is_only_under(Path::new("./a/unsorted/b/c.txt")));
+    assert!(!options.is_only_under(Path::new("financial/invoice.pdf")));
+    assert!(!options.is_only_under(Path::new("uncategorized-old/x.txt")));
+    assert!(!options.is_only_under(Path::new("a/notes.txt")));
+
+    assert!(options.may_contain_only_under(Path::new("")));
+    assert!(options.may_contain_only_under(Path::new("a")));
+    assert!(options.may_contain_only_under(Path::new("a/unsorted/deep")));
+    assert!(!options.may_contain_only_under(Path::new("financial")));
+
+    let everything = WalkOptions::default();
+    assert!(everything.is_only_under(Path::new("financial/invoice.pdf")));
+    assert!(everything.may_contain_only_under(Path::new("financial")));
+}
+
+#[test]
+fn test_extraction_error_classification() {
+    let classify = |kind, msg: &str| ExtractionError::from(io::Error::new(kind, msg.to_string()));
+
+    let corrupt = classify(io::ErrorKind::InvalidData, "bad xref table");
+    assert_eq!(corrupt.tag(), Some(CORRUPT_TAG));
+    assert_eq!(corrupt.to_string(), "corrupt file: bad xref table");
+    assert!(!corrupt.is_retryable());
+
+    let truncated = classify(io::ErrorKind::UnexpectedEof, "truncated zip");
+    assert!(matches!(truncated, ExtractionError::Corrupt(_)));
+
+    let unsupported = classify(io::ErrorKind::Unsupported, "no handler");
+    assert_eq!(unsupported.tag(), Some(UNSUPPORTED_TAG));
+
+    let timeout = classify(io::ErrorKind::TimedOut, "slow");
+    assert!(matches!(timeout, ExtractionError::Timeout));
+    assert!(timeout.is_retryable());
+    assert_eq!(timeout.tag(), None);
+
+    let io = classify(io::ErrorKind::PermissionDenied, "denied");
+    assert!(matches!(io, ExtractionError::Io(_)));
+    assert!(io.is_retryable());
+
+    assert_eq!(ExtractionError::Encrypted.tag(), Some(ENCRYPTED_TAG));
+    assert!(!ExtractionError::Encrypted.is_retryable());
+}
+
+#[cfg(test)]
+fn walk_fixture(name: &str) -> PathBuf {
+    let root = std::env::temp_dir().join(format!("cognify-walk-{}-{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&root);
+    for dir in ["docs/deep/deeper", ".hidden", "cache", "repo/src"] {
+        fs::create_dir_all(root.join(dir)).unwrap();
+    }
+    for file in [
+        "a.txt",
+        "docs/b.pdf",
+        "docs/deep/c.md",
+        "docs/deep/deeper/d.md",
+        ".hidden/e.txt",
+        ".dotfile",
+        "cache/f.txt",
+        "repo/Cargo.toml",
+        "repo/src/lib.rs",
+    ] {
+        fs::write(root.join(file), file).unwrap();
+    }
+    root
+}
+
+#[cfg(test)]
+fn walked(root: &Path, options: &WalkOptions) -> Vec<String> {
+    walk_files(root, options)
+        .map(|meta| {
+            let path = meta.unwrap().path;
+            path.strip_prefix(root)
+                .unwrap()
+                .to_string_lossy()
+                .replace('\\', "/")
+        })
+        .collect()
+}
+
+#[test]
+fn test_walk_files_defaults_skip_hidden() {
+    let root = walk_fixture("defaults");
+    assert_eq!(
+        walked(&root, &WalkOptions::default()),
+        vec![
+            "a.txt",
+            "cache/f.txt",
+            "docs/b.pdf",
+            "docs/deep/c.md",
+            "docs/deep/deeper/d.md",
+            "repo/Cargo.toml",
+            "repo/src/lib.rs",
+        ]
+    );
+
+    let options = WalkOptions {
+        include_hidden: true,
+        ..WalkOptions::default()
+    };
+    let files = walked(&root, &options);
+    assert!(files.contains(&".dotfile".to_string()));
+    assert!(files.contains(&".hidden/e.txt".to_string()));
+    fs::remove_dir_all(&root).unwrap();
+}
+
+#[test]
+fn test_walk_files_builds_file_meta_with_hash() {
+    let root = walk_fixture("meta");
+    let first = walk_files(&root, &WalkOptions::default())
+        .next()
+        .unwrap()
+        .unwrap();
+    assert_eq!(first.path, root.join("a.txt"));
+    assert_eq!(first.extension.as_deref(), Some("txt"));
+    assert_eq!(first.size, "a.txt".len() as u64);
+    assert_eq!(
+        first.hash,
+        Some(blake3::hash(b"a.txt").to_hex().to_string())
+    );
+    assert_eq!(
+        first.hash,
+        Some(compute_file_hash(&root.join("a.txt")).unwrap())
+    );
+
+    let options = WalkOptions {
+        hash: false,
+        ..WalkOptions::default()
+    };
+    let first = walk_files(&root, &options).next().unwrap().unwrap();
+    assert_eq!(first.hash, None);
+    fs::remove_dir_all(&root).unwrap();
+}
+
+#[test]
+fn test_walk_files_max_depth() {
+    let root = walk_fixture("depth");
+    let options = WalkOptions {
+        max_depth: Some(2),
+        ..WalkOptions::default()
+    };
+    assert_eq!(
+        walked(&root, &options),
+        vec!["a.txt", "cache/f.txt", "docs/b.pdf", "repo/Cargo.toml"]
+    );
+    fs::remove_dir_all(&root).unwrap();
+}
+
+#[cfg(unix)]
+#[test]
+fn test_walk_files_symlinks() {
+    let root = walk_fixture("symlinks");
+    std::os::unix::fs::symlink(root.join("docs"), root.join("link")).unwrap();
+    std::os::unix::fs::symlink(&root, root.join("docs/loop")).unwrap();
+
+    let files = walked(&root, &WalkOptions::default());
+    assert!(!files
+        .iter()
+        .any(|f| f.starts_with("link") || f.contains("loop")));
+
+    let options = WalkOptions {
+        follow_symlinks: true,
+        ..WalkOptions::default()
+    };
+    let files = walked(&root, &options);
+    assert_eq!(files.iter().filter(|f| f.ends_with("b.pdf")).count(), 1);
+
+    // A symlinked root is always followed.
+    let alias = root.with_extension("alias");
+    let _ = fs::remove_file(&alias);
+    std::os::unix::fs::symlink(&root, &alias).unwrap();
+    assert_eq!(
+        walked(&alias, &WalkOptions::default()),
+        walked(&root, &WalkOptions::default())
+    );
+
+    fs::remove_file(&alias).unwrap();
+    fs::remove_dir_all(&root).unwrap();
+}
+
+#[cfg(unix)]
+#[test]
+fn test_walk_files_entry_error_keeps_siblings() {
+    let root = walk_fixture("errors");
+    std::os::unix::fs::symlink(root.join("missing"), root.join("docs/broken")).unwrap();
+    let options = WalkOptions {
+        follow_symlinks: true,
+        ..WalkOptions::default()
+    };
+    let results: Vec<_> = walk_files(&root, &options).collect();
+    assert_eq!(results.iter().filter(|r| r.is_err()).count(), 1);
+    let files: Vec<_> = results
+        .into_iter()
+        .filter_map(Result::ok)
+        .map(|meta| meta.path)
+        .collect();
+    assert!(files.contains(&root.join("docs/b.pdf")));
+    assert!(files.contains(&root.join("docs/deep/c.md")));
+
+    assert!(walk_files(&root.join("missing"), &options)
+        .next()
+        .unwrap()
+        .is_err());
+    fs::remove_dir_all(&root).unwrap();
+}
+
+#[test]
+fn test_walk_files_extension_and_ignore_filters() {
+    let root = walk_fixture("filters");
+    let options = WalkOptions {
+        include_ext: vec!["MD".to_string()],
+        ..WalkOptions::default()
+    };
+    assert_eq!(
+        walked(&root, &options),
+        vec!["docs/deep/c.md", "docs/deep/deeper/d.md"]
+    );
+
+    let options = WalkOptions {
+        exclude_ext: vec![".md".to_string(), "pdf".to_string(), "rs".to_string()],
+        ignore: DirGlobs::new(&["*.toml", "cache/*"]).unwrap(),
+        ..WalkOptions::default()
+    };
+    assert_eq!(walked(&root, &options), vec!["a.txt"]);
+    fs::remove_dir_all(&root).unwrap();
+}
+
+#[test]
+fn test_walk_files_exclude_dirs_and_only_under() {
+    let root = walk_fixture("dirs");
+    let options = WalkOptions {
+        exclude_dirs: DirGlobs::new(&["cache", "docs/deep"]).unwrap(),
+        ..WalkOptions::default()
+    };
+    assert_eq!(
+        walked(&root, &options),
+        vec!["a.txt", "docs/b.pdf", "repo/Cargo.toml", "repo/src/lib.rs"]
+    );
+
+    let options = WalkOptions {
+        only_under: vec![PathBuf::from("docs/deep")],
+        ..WalkOptions::default()
+    };
+    assert_eq!(
+        walked(&root, &options),
+        vec!["docs/deep/c.md", "docs/deep/deeper/d.md"]
+    );
+    fs::remove_dir_all(&root).unwrap();
+}
+
+#[test]
+fn test_walk_files_protected_dirs() {
+    let root = walk_fixture("protected");
+    let protected: Vec<_> = walk_files(&root, &WalkOptions::default())
+        .map(Result::unwrap)
+        .filter(|meta| meta.protected)
+        .map(|meta| meta.path)
+        .collect();
+    assert_eq!(
+        protected,
+        vec![root.join("repo/Cargo.toml"), root.join("repo/src/lib.rs")]
+    );
+
+    let options = WalkOptions {
+        skip_protected: true,
+        ..WalkOptions::default()
+    };
+    assert!(!walked(&root, &options)
+        .iter()
+        .any(|f| f.starts_with("repo")));
+
+    // The root itself is never treated as protected.
+    let repo = root.join("repo");
+    assert!(walk_files(&repo, &options).all(|meta| !meta.unwrap().protected));
+    fs::remove_dir_all(&root).unwrap();
+}
+
+#[test]
+fn test_state_paths_layout() {
+    let base = Path::new("/data/docs");
+    let state = StatePaths::new(base, None);
+    assert_eq!(state.root(), Path::new("/data/docs/.cognifs"));
+    assert_eq!(
+        state.index_checkpoint(),
+        Path::new("/data/docs/.cognifs/index-checkpoint.json")
+    );
+    assert_eq!(state.plans_dir(), Path::new("/data/docs/.cognifs/plans"));
+
+    let relative = StatePaths::new(base, Some(Path::new("state")));
+    assert_eq!(relative.root(), Path::new("/data/docs/state"));
+    let absolute = StatePaths::new(base, Some(Path::new("/var/lib/cognifs")));
+    assert_eq!(absolute.cache_dir(), Path::new("/var/lib/cognifs/cache"));
+
+    assert!(state.contains(Path::new("/data/docs/.cognifs/undo.log")));
+    assert!(state.contains(Path::new("/data/docs/sub/.cognifs/plans")));
+    assert!(!state.contains(Path::new("/data/docs/report.pdf")));
+}
+
+#[test]
+fn test_state_paths_created_lazily() {
+    let base = std::env::temp_dir().join(format!("cognify-state-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&base);
+    let state = StatePaths::new(&base, None);
+    assert!(!state.root().exists());
+
+    let log = state.ensure_file_parent(state.undo_log()).unwrap();
+    assert!(state.root().is_dir());
+    assert!(!log.exists());
+
+    let plans = state.ensure_dir(state.plans_dir()).unwrap();
+    assert!(plans.is_dir());
+    assert_eq!(plans, state.plans_dir());
+    fs::remove_dir_all(&base).unwrap();
+}
+
+#[test]
+fn test_walk_files_skips_state_dir() {
+    let root = walk_fixture("state");
+    let state = StatePaths::new(&root, None);
+    let log = state.ensure_file_parent(state.undo_log()).unwrap();
+    fs::write(log, "moves").unwrap();
+    let custom = StatePaths::new(&root, Some(Path::new("docs/deep")));
+
+    let options = WalkOptions {
+        include_hidden: true,
+        state_dir: Some(PathBuf::from("docs/deep")),
+        ..WalkOptions::default()
+    };
+    let files = walked(&root, &options);
+    assert!(files.contains(&".hidden/e.txt".to_string()));
+    assert!(!files.iter().any(|f| f.starts_with(".cognifs")));
+    assert!(!files.iter().any(|f| custom.contains(&root.join(f))));
+    assert!(files.contains(&"docs/b.pdf".to_string()));
+    fs::remove_dir_all(&root).unwrap();
+}
+
+#[test]
+fn test_looks_binary_detects_binary_content() {
+    let mut elf = vec![0x7f, b'E', b'L', b'F', 2, 1, 1, 0];
+    elf.extend(std::iter::repeat_n(0u8, 64));
+    assert!(looks_binary(&elf));
+
+    let noise: Vec<u8> = (0..512u32).map(|i| (i * 37 % 256) as u8 | 0x80).collect();
+    assert!(looks_binary(&noise));
+
+    assert!(!looks_binary(b"plain text\nwith\ttabs\r\n"));
+    assert!(!looks_binary(
+        "caf\u{e9} na\u{ef}ve \u{65e5}\u{672c}\u{8a9e}".as_bytes()
+    ));
+    assert!(!looks_binary(b""));
+}
+
+#[test]
+fn test_looks_binary_accepts_utf16_with_bom() {
+    let mut le = vec![0xFF, 0xFE];
+    le.extend("UTF-16 notes\r\n".encode_utf16().flat_map(u16::to_le_bytes));
+    assert!(!looks_binary(&le));
+    assert_eq!(sniff_extension(&le), Some("txt"));
+
+    let mut be = vec![0xFE, 0xFF];
+    be.extend("UTF-16 notes\r\n".encode_utf16().flat_map(u16::to_be_bytes));
+    assert!(!looks_binary(&be));
+    assert_eq!(
+        text_or_binary(&be),
+        ("UTF-16 notes\r\n".to_string(), vec![])
+    );
+}
+
+#[test]
+fn test_read_text_tags_binary_files() {
+    let dir = std::env::temp_dir().join(format!("cognify-binary-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let blob = dir.join("firmware.bin");
+    let mut bytes = b"\x7fELF\x02\x01\x01".to_vec();
+    bytes.extend(vec![0u8; 4096]);
+    fs::write(&blob, &bytes).unwrap();
+    let log = dir.join("device.log");
+    fs::write(&log, &bytes).unwrap();
+    let notes = dir.join("notes.dat");
+    fs::write(&notes, "readable notes").unwrap();
+
+    let config = ExtractionConfig::default();
+    for path in [&blob, &log] {
+        let (text, tags) = config.read_text(path).unwrap();
+        assert!(text.is_empty());
+        assert_eq!(tags, vec![BINARY_TAG]);
+    }
+    assert_eq!(
+        config.read_text(&notes).unwrap(),
+        ("readable notes".to_string(), vec![])
+    );
+
+    fs::remove_dir_all(&dir).unwrap();
+}